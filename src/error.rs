@@ -13,11 +13,23 @@ pub enum K8sError {
     #[fail(display = "container has an unsupported runtime: {}", _0)]
     UnsupportedContainerRuntime(String),
 
+    #[fail(
+        display = "sandboxed runtime {} not supported for in-guest inspection, only the host side of the sandbox is visible",
+        _0
+    )]
+    SandboxedRuntime(String),
+
     #[fail(display = "field {} has an unsupported format: {}", field, val)]
     UnsupportedFieldFormat { field: String, val: String },
 
     #[fail(display = "field {} is missing or is null", _0)]
     MissingOrNullField(String),
+
+    #[fail(display = "pod is not ready after waiting for {} seconds", _0)]
+    PodNotReady(u64),
+
+    #[fail(display = "no pod found with IP address {}", _0)]
+    NoPodWithIp(String),
 }
 
 impl From<kubeclient::errors::Error> for K8sError {
@@ -39,10 +51,72 @@ pub enum HostCmdError {
     CmdInvalid(String),
 }
 
+#[derive(Debug, Fail)]
+pub enum SelfContainerError {
+    #[fail(display = "failed to detect the id of the current container from /proc/self")]
+    IdMissing,
+
+    #[fail(display = "the current container has an unsupported runtime: {}", _0)]
+    UnsupportedRuntime(String),
+}
+
+#[derive(Debug, Fail)]
+pub enum PodmanError {
+    #[fail(display = "invalid response from the podman API")]
+    InvalidResponse,
+
+    #[fail(
+        display = "podman API request failed with status {}: {}",
+        status, message
+    )]
+    RequestFailed { status: u16, message: String },
+
+    #[fail(display = "field {} is missing from the podman container info", _0)]
+    MissingField(&'static str),
+}
+
 #[derive(Debug, Fail, Copy, Clone)]
 #[fail(display = "failed to extract veth interfaces from the output of `ip link/addr show`")]
 pub struct IpLinkOrAddrShowParseErr;
 
+#[derive(Debug, Fail)]
+#[fail(display = "failed to find a container using node interface {}", _0)]
+pub struct IntfOwnerMissingErr(pub String);
+
+#[derive(Debug, Fail)]
+#[fail(display = "failed to find a container with IP address {}", _0)]
+pub struct IpOwnerMissingErr(pub String);
+
+#[derive(Debug, Fail, Copy, Clone)]
+#[fail(display = "failed to parse the content of /proc/net/dev")]
+pub struct ProcNetDevParseErr;
+
+#[derive(Debug, Fail, Copy, Clone)]
+#[fail(display = "invalid arguments: {}", _0)]
+pub struct InvalidArgsErr(pub &'static str);
+
+#[derive(Debug, Fail)]
+#[fail(display = "no running container found in pod {}", _0)]
+pub struct NoRunningContainerErr(pub String);
+
+#[derive(Debug, Fail)]
+#[fail(display = "the inspection did not finish within {} seconds", _0)]
+pub struct TargetTimeoutErr(pub u64);
+
 #[derive(Debug, Fail, Copy, Clone)]
-#[fail(display = "failed to find a node veth interface with ifindex {}", _0)]
-pub struct IntfMissingErr(pub u16);
+#[fail(
+    display = "unsupported output version {}, the report was saved by a newer cniguru",
+    _0
+)]
+pub struct UnsupportedOutputVersionErr(pub u32);
+
+#[derive(Debug, Fail)]
+#[fail(display = "more than one result with key {}", _0)]
+pub struct DuplicateKeyErr(pub String);
+
+#[derive(Debug, Fail)]
+#[fail(
+    display = "no unix socket at {}, make sure the collector is running and listening on it",
+    _0
+)]
+pub struct OutputSocketMissingErr(pub String);
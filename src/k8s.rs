@@ -1,11 +1,20 @@
-use super::error::K8sError;
-use super::{Container, ContainerRuntime};
+use super::error::{HostCmdError, K8sError};
+use super::{map_concurrently, run_host_prog, Config, Container, ContainerRuntime};
 use failure::Error;
+use kubeclient::resources::ListQuery;
 use kubeclient::{self, prelude::*};
 use std::env;
 use std::fs::File;
+use std::thread;
+use std::time::{Duration, Instant};
 use url::Url;
 
+/// How often to poll the kubernetes API while waiting for a pod to come up
+const WAIT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The maximum number of selected pods whose containers are looked up at the same time
+const MAX_CONCURRENT_PODS: usize = 8;
+
 pub struct Pod<'a> {
     pub name: &'a str,
     pub namespace: &'a str,
@@ -22,68 +31,562 @@ impl<'a> Pod<'a> {
         Self { name, namespace }
     }
 
-    /// Return the path to the config file as String
+    /// Fetch the k8s pod with the given name on the given namespace
+    fn get_pod(&self) -> Result<kubeclient::resources::Pod, K8sError> {
+        let kube = load_kube_client()?;
+        let pod = kube.namespace(self.namespace).pods().get(self.name)?;
+        trace!("k8s response:\n{:#?}", pod);
+        Ok(pod)
+    }
+
+    /// Extract info about the containers in the pod
+    pub fn containers(&self, cfg: &Config) -> Result<Vec<Container>, Error> {
+        let pod = self.get_pod()?;
+        extract_container_info(cfg, pod)
+    }
+
+    /// Extract info about the ephemeral containers in the pod
     ///
-    /// The user can specify a kubeconfig file by setting the `KUBECONFIG` environment variable
-    /// If no file is specified, look for a file named `config` in the `$HOME/.kube` directory.
-    /// Next, try to use `/etc/kubernetes/admin.conf` and see if that works out
-    fn get_kubeconfig_path(&self) -> Result<String, K8sError> {
-        let key = "KUBECONFIG";
-        match env::var(key) {
-            Ok(val) => {
-                debug!("using kubeconfig from ${}: {}", key, val);
-                Ok(val)
-            }
-            Err(_) => {
-                // use `$HOME/.kube/config` if it exist
-                if let Some(dir) = env::home_dir() {
-                    let cfg_file_path = dir.join(".kube/config");
-                    match cfg_file_path.is_file() {
-                        true => {
-                            let cfg = cfg_file_path.to_string_lossy().to_string();
-                            debug!("using kubeconfig: {}", cfg);
-                            return Ok(cfg);
-                        }
-                        false => debug!("$HOME/.kube/config does not exist or is not a file"),
+    /// The kubernetes client does not know about the ephemeral containers so `kubectl`
+    /// is used to fetch the pod instead. The pod level info is copied from `template`,
+    /// one of the regular containers of the pod
+    pub fn ephemeral_containers(
+        &self,
+        cfg: &Config,
+        template: &Container,
+    ) -> Result<Vec<Container>, Error> {
+        let output = kubectl(&["get", "pod", self.name, "-n", self.namespace, "-o", "json"])?;
+        let pod: serde_json::Value = serde_json::from_str(&output)?;
+        let mut res = vec![];
+        for (name, runtime, id) in parse_ephemeral_container_ids(&pod)? {
+            let mut container = Container::new(cfg, id, runtime)?;
+            container.container_name = name;
+            container.node_name = template.node_name.clone();
+            container.spec_host_network = template.spec_host_network;
+            container.networks_annotation = template.networks_annotation.clone();
+            container.pod_name = template.pod_name.clone();
+            container.namespace = template.namespace.clone();
+            container.pod_ip = template.pod_ip.clone();
+            container.ephemeral = true;
+            res.push(container);
+        }
+        Ok(res)
+    }
+
+    /// Same as `containers()` but, while the pod is still coming up, keep polling the
+    /// kubernetes API until every container has an id and a running process
+    pub fn wait_for_containers(
+        &self,
+        cfg: &Config,
+        timeout: Duration,
+    ) -> Result<Vec<Container>, Error> {
+        let start = Instant::now();
+        loop {
+            match self.containers(cfg) {
+                // docker reports pid 0 for containers that are not running
+                Ok(containers) => {
+                    if containers.iter().all(|c| c.pid != 0) {
+                        return Ok(containers);
+                    }
+                    debug!("pod {} has containers that are not running yet", self.name);
+                    if start.elapsed() >= timeout {
+                        Err(K8sError::PodNotReady(timeout.as_secs()))?
                     }
-                } else {
-                    debug!("could not find the user's home directory");
                 }
-
-                // use `/etc/kubernetes/admin.conf` if it exist and the user can open it
-                let file = "/etc/kubernetes/admin.conf";
-                match File::open(file) {
-                    Ok(_) => {
-                        debug!("using kubeconfig from {}", file);
-                        Ok(file.to_string())
+                Err(e) => {
+                    if !is_pod_not_ready_err(&e) || start.elapsed() >= timeout {
+                        return Err(e);
                     }
-                    Err(e) => {
-                        debug!("Failed to open {}: {}", file, e);
-                        Err(K8sError::KubeconfigMissing)
+                    debug!("pod {} is not ready yet: {}", self.name, e);
+                }
+            }
+            thread::sleep(WAIT_POLL_INTERVAL);
+        }
+    }
+}
+
+/// Check if the error is expected while the pod is still coming up, e.g. the container ids
+/// are not yet set in the pod status or the containers are not yet known to docker
+fn is_pod_not_ready_err(e: &Error) -> bool {
+    match e.downcast_ref::<K8sError>() {
+        Some(K8sError::MissingOrNullField(_)) => true,
+        Some(_) => false,
+        None => e.downcast_ref::<HostCmdError>().is_some(),
+    }
+}
+
+/// The containers of a pod together with the pod name, or the error hit while inspecting it
+pub type PodContainers = (String, Result<Vec<Container>, Error>);
+
+/// A set of k8s pods selected using a label selector, e.g. all the pods of a deployment
+pub struct PodSelector<'a> {
+    pub selector: &'a str,
+    pub namespace: &'a str,
+}
+
+impl<'a> PodSelector<'a> {
+    pub fn new(selector: &'a str, namespace: Option<&'a str>) -> Self {
+        // use `default` if no other namespace is specified
+        let namespace = match namespace {
+            Some(ns) => ns,
+            None => "default",
+        };
+        debug!("pod selector {}, namespace {}", selector, namespace);
+        Self {
+            selector,
+            namespace,
+        }
+    }
+
+    /// Fetch the k8s pods matching the label selector on the given namespace
+    fn list_pods(&self) -> Result<Vec<kubeclient::resources::Pod>, K8sError> {
+        let kube = load_kube_client()?;
+        let query = ListQuery::default().label_selector(self.selector);
+        let pods = kube.namespace(self.namespace).pods().list(Some(&query))?;
+        trace!("k8s response:\n{:#?}", pods);
+        Ok(pods)
+    }
+
+    /// Extract info about the containers of every selected pod
+    ///
+    /// The result is returned separately for every pod, identified by its name,
+    /// so a pod that can't be inspected (e.g. it's terminating) does not affect the others
+    ///
+    /// The lookups of the containers, e.g. `docker inspect`, are independent so they are
+    /// run concurrently, the pods are returned in the order they were listed
+    pub fn containers(&self, cfg: &Config) -> Result<Vec<PodContainers>, Error> {
+        let cfg = cfg.clone();
+        let res = map_concurrently(self.list_pods()?, MAX_CONCURRENT_PODS, move |pod| {
+            let name = pod.metadata.name.clone().unwrap_or_default();
+            (name, extract_container_info(&cfg, pod))
+        });
+        Ok(res)
+    }
+}
+
+/// The k8s pods with a given IP address, e.g. one seen in a packet capture
+///
+/// More than one pod can match, e.g. the pods using the host network share the node IP
+pub struct PodsByIp<'a> {
+    pub ip: std::net::IpAddr,
+    pub namespace: &'a str,
+}
+
+impl<'a> PodsByIp<'a> {
+    pub fn new(ip: std::net::IpAddr, namespace: Option<&'a str>) -> Self {
+        // use `default` if no other namespace is specified
+        let namespace = match namespace {
+            Some(ns) => ns,
+            None => "default",
+        };
+        debug!("pod IP {}, namespace {}", ip, namespace);
+        Self { ip, namespace }
+    }
+
+    /// Extract info about the containers of every pod with the IP address
+    ///
+    /// The kubernetes client does not know about `podIPs` so `kubectl` is used to list the
+    /// pods, the matching ones are then inspected like with `PodSelector`
+    pub fn containers(&self, cfg: &Config) -> Result<Vec<PodContainers>, Error> {
+        let output = kubectl(&["get", "pods", "-n", self.namespace, "-o", "json"])?;
+        let pods: serde_json::Value = serde_json::from_str(&output)?;
+        let names = pod_names_by_ip(&pods, &self.ip);
+        if names.is_empty() {
+            Err(K8sError::NoPodWithIp(self.ip.to_string()))?
+        }
+        let res = names
+            .into_iter()
+            .map(|name| {
+                let res = Pod::new(&name, Some(self.namespace)).containers(cfg);
+                (name, res)
+            })
+            .collect();
+        Ok(res)
+    }
+}
+
+/// Get the names of the pods having the IP address as `podIP` or in `podIPs`
+/// from the json of a pod list
+pub fn pod_names_by_ip(pods: &serde_json::Value, ip: &std::net::IpAddr) -> Vec<String> {
+    let matches = |v: &serde_json::Value| {
+        v.as_str()
+            .and_then(|s| s.parse::<std::net::IpAddr>().ok())
+            .as_ref()
+            == Some(ip)
+    };
+    let mut res = vec![];
+    for pod in pods["items"].as_array().into_iter().flatten() {
+        let status = &pod["status"];
+        let found = matches(&status["podIP"])
+            || status["podIPs"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .any(|v| matches(&v["ip"]));
+        if let (true, Some(name)) = (found, pod["metadata"]["name"].as_str()) {
+            res.push(name.to_string());
+        }
+    }
+    res
+}
+
+/// Return the path to the config file as String
+///
+/// The user can specify a kubeconfig file by setting the `KUBECONFIG` environment variable
+/// If no file is specified, look for a file named `config` in the `$HOME/.kube` directory.
+/// Next, try to use `/etc/kubernetes/admin.conf` and see if that works out
+fn get_kubeconfig_path() -> Result<String, K8sError> {
+    let key = "KUBECONFIG";
+    match env::var(key) {
+        Ok(val) => {
+            debug!("using kubeconfig from ${}: {}", key, val);
+            Ok(val)
+        }
+        Err(_) => {
+            // use `$HOME/.kube/config` if it exist
+            if let Some(dir) = env::home_dir() {
+                let cfg_file_path = dir.join(".kube/config");
+                match cfg_file_path.is_file() {
+                    true => {
+                        let cfg = cfg_file_path.to_string_lossy().to_string();
+                        debug!("using kubeconfig: {}", cfg);
+                        return Ok(cfg);
                     }
+                    false => debug!("$HOME/.kube/config does not exist or is not a file"),
+                }
+            } else {
+                debug!("could not find the user's home directory");
+            }
+
+            // use `/etc/kubernetes/admin.conf` if it exist and the user can open it
+            let file = "/etc/kubernetes/admin.conf";
+            match File::open(file) {
+                Ok(_) => {
+                    debug!("using kubeconfig from {}", file);
+                    Ok(file.to_string())
+                }
+                Err(e) => {
+                    debug!("Failed to open {}: {}", file, e);
+                    Err(K8sError::KubeconfigMissing)
                 }
             }
         }
     }
+}
 
-    /// Fetch the k8s pod with the given name on the given namespace
-    fn get_pod(&self) -> Result<kubeclient::resources::Pod, K8sError> {
-        let cfg = self.get_kubeconfig_path()?;
-        let kube = Kubernetes::load_conf(&cfg)?;
-        let pod = kube.namespace(self.namespace).pods().get(self.name)?;
-        trace!("k8s response:\n{:#?}", pod);
-        Ok(pod)
+/// Run `kubectl` with the kubeconfig found by `get_kubeconfig_path()`
+///
+/// The arguments are passed as they are, without splitting them on spaces, since the names
+/// may come from the pod annotations
+fn kubectl(args: &[&str]) -> Result<String, Error> {
+    let cfg = get_kubeconfig_path()?;
+    let mut all = vec!["--kubeconfig", &cfg[..]];
+    all.extend_from_slice(args);
+    run_host_prog("kubectl", &all)
+}
+
+/// Create a kubernetes client using the kubeconfig found by `get_kubeconfig_path()`
+fn load_kube_client() -> Result<Kubernetes, K8sError> {
+    let cfg = get_kubeconfig_path()?;
+    Ok(Kubernetes::load_conf(&cfg)?)
+}
+
+/// Check the kubernetes API server is healthy, used by `selftest`
+///
+/// Returns the kubeconfig used or `None` if no kubeconfig could be found
+pub fn check_api() -> Result<Option<String>, K8sError> {
+    let cfg = match get_kubeconfig_path() {
+        Ok(v) => v,
+        Err(K8sError::KubeconfigMissing) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let kube = Kubernetes::load_conf(&cfg)?;
+    if kube.healthy()? {
+        Ok(Some(cfg))
+    } else {
+        Err(K8sError::KubeclientError(
+            "the API server is not healthy".into(),
+        ))
     }
+}
 
-    /// Extract info about the containers in the pod
-    pub fn containers(&self) -> Result<Vec<Container>, Error> {
-        let pod = self.get_pod()?;
-        extract_container_info(pod)
+/// The pod annotation used by Multus to reference the extra networks of a pod
+const NETWORKS_ANNOTATION: &str = "k8s.v1.cni.cncf.io/networks";
+
+/// An extra network of a Multus pod, i.e. a reference to a `NetworkAttachmentDefinition`,
+/// and the name of the container interface attached to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkAttachment {
+    pub namespace: String,
+    pub name: String,
+    pub interface: String,
+    pub config: Option<serde_json::Value>,
+}
+
+/// Parse the Multus networks annotation of a pod in the given namespace
+///
+/// Both the comma separated `[<namespace>/]<name>[@<interface>]` and the json list formats
+/// are supported. The interfaces not named explicitly are `net1`, `net2`, ... in order,
+/// as named by Multus
+pub fn parse_networks_annotation(
+    val: &str,
+    pod_namespace: &str,
+) -> Result<Vec<NetworkAttachment>, Error> {
+    let err = || K8sError::UnsupportedFieldFormat {
+        field: format!("pod.metadata.annotations.{}", NETWORKS_ANNOTATION),
+        val: val.to_string(),
+    };
+    let default_intf = |idx: usize| format!("net{}", idx + 1);
+
+    let mut res = vec![];
+    if val.trim_start().starts_with('[') {
+        let items: Vec<serde_json::Value> = serde_json::from_str(val).map_err(|_| err())?;
+        for (idx, item) in items.iter().enumerate() {
+            let field = |k: &str| item.get(k).and_then(|v| v.as_str()).map(|v| v.to_string());
+            res.push(NetworkAttachment {
+                namespace: field("namespace").unwrap_or_else(|| pod_namespace.to_string()),
+                name: field("name").ok_or_else(err)?,
+                interface: field("interface").unwrap_or_else(|| default_intf(idx)),
+                config: None,
+            });
+        }
+    } else {
+        let items = val.split(',').map(|v| v.trim()).filter(|v| !v.is_empty());
+        for (idx, item) in items.enumerate() {
+            let (net, interface) = match item.find('@') {
+                Some(pos) => (&item[..pos], item[pos + 1..].to_string()),
+                None => (item, default_intf(idx)),
+            };
+            let (namespace, name) = match net.find('/') {
+                Some(pos) => (net[..pos].to_string(), net[pos + 1..].to_string()),
+                None => (pod_namespace.to_string(), net.to_string()),
+            };
+            if name.is_empty() {
+                Err(err())?
+            }
+            res.push(NetworkAttachment {
+                namespace,
+                name,
+                interface,
+                config: None,
+            });
+        }
+    }
+    Ok(res)
+}
+
+/// Fetch the CNI config of a `NetworkAttachmentDefinition`
+///
+/// `kubectl` is used as the kubernetes client used for pods has no support for custom resources
+pub fn get_network_attachment_config(
+    namespace: &str,
+    name: &str,
+) -> Result<Option<serde_json::Value>, Error> {
+    let output = kubectl(&[
+        "get",
+        "network-attachment-definitions.k8s.cni.cncf.io",
+        name,
+        "-n",
+        namespace,
+        "-o",
+        "json",
+    ])?;
+    let nad: serde_json::Value = serde_json::from_str(&output)?;
+    // the config is a json document serialized as a string, it may be missing if the config
+    // is stored on the nodes
+    match nad["spec"]["config"].as_str() {
+        Some(s) => Ok(Some(serde_json::from_str(s)?)),
+        None => Ok(None),
+    }
+}
+
+/// The QoS class of a pod and the names of the NetworkPolicies selecting it
+#[derive(Debug, Clone)]
+pub struct PodPolicies {
+    pub qos_class: Option<String>,
+    pub network_policies: Vec<String>,
+}
+
+/// Fetch the QoS class of the pod and the NetworkPolicies of its namespace selecting it
+///
+/// `kubectl` is used as the kubernetes client knows neither the `qosClass` nor the pod
+/// selectors of the policies
+pub fn get_pod_policies(namespace: &str, name: &str) -> Result<PodPolicies, Error> {
+    let output = kubectl(&["get", "pod", name, "-n", namespace, "-o", "json"])?;
+    let pod: serde_json::Value = serde_json::from_str(&output)?;
+    let output = kubectl(&["get", "networkpolicies", "-n", namespace, "-o", "json"])?;
+    let policies: serde_json::Value = serde_json::from_str(&output)?;
+    Ok(PodPolicies {
+        qos_class: pod["status"]["qosClass"].as_str().map(|s| s.to_string()),
+        network_policies: policies_selecting(&policies, &pod["metadata"]["labels"]),
+    })
+}
+
+/// Get the names of the NetworkPolicies, from the json of a policy list, whose
+/// `podSelector` matches the pod labels
+pub fn policies_selecting(policies: &serde_json::Value, labels: &serde_json::Value) -> Vec<String> {
+    policies["items"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|p| selector_matches(&p["spec"]["podSelector"], labels))
+        .filter_map(|p| p["metadata"]["name"].as_str().map(|s| s.to_string()))
+        .collect()
+}
+
+/// Check a label selector against the labels, the empty selector matches every pod
+///
+/// The requirements of `matchLabels` and `matchExpressions` are ANDed, an expression with
+/// an unknown operator never matches
+fn selector_matches(selector: &serde_json::Value, labels: &serde_json::Value) -> bool {
+    let label = |key: &str| labels[key].as_str();
+    let match_labels = selector["matchLabels"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .all(|(k, v)| label(k).is_some() && label(k) == v.as_str());
+    let match_expressions = selector["matchExpressions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .all(|e| {
+            let key = e["key"].as_str().unwrap_or_default();
+            let in_values = || {
+                e["values"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .any(|v| label(key).is_some() && v.as_str() == label(key))
+            };
+            match e["operator"].as_str() {
+                Some("In") => in_values(),
+                Some("NotIn") => !in_values(),
+                Some("Exists") => label(key).is_some(),
+                Some("DoesNotExist") => label(key).is_none(),
+                _ => false,
+            }
+        });
+    match_labels && match_expressions
+}
+
+/// Parse a container id from the pod status, e.g. `docker://c6671e7930e7181d7e..`
+///
+/// `obj_path` is the json path to the id, used for details about errors
+fn parse_container_id(
+    raw_cid: &str,
+    obj_path: String,
+) -> Result<(ContainerRuntime, String), Error> {
+    // the containerID is expected to have an URL format, e.g. `docker://<id>`, but a bare
+    // id is accepted too in case a runtime reports it without the scheme
+    let cid = match Url::parse(raw_cid) {
+        Ok(v) => v,
+        Err(e) => return bare_container_id(raw_cid, obj_path, e),
+    };
+
+    let runtime = match cid.scheme() {
+        "docker" => ContainerRuntime::Docker,
+        s if SANDBOXED_RUNTIME_SCHEMES.contains(&s) => {
+            Err(K8sError::SandboxedRuntime(s.to_string()))?
+        }
+        other @ _ => Err(K8sError::UnsupportedContainerRuntime(other.to_string()))?,
+    };
+
+    // e.g. `docker:<id>` has no host, the id is the path
+    let id = match cid.host_str() {
+        Some(s) => s.to_string(),
+        None => match cid.path().trim_matches('/') {
+            p if is_bare_container_id(p) => p.to_string(),
+            _ => Err(K8sError::UnsupportedFieldFormat {
+                field: obj_path,
+                val: raw_cid.to_string(),
+            })?,
+        },
+    };
+
+    Ok((runtime, id))
+}
+
+/// Use a containerID without a scheme as a docker container id, with a warning
+fn bare_container_id(
+    raw_cid: &str,
+    obj_path: String,
+    e: url::ParseError,
+) -> Result<(ContainerRuntime, String), Error> {
+    if !is_bare_container_id(raw_cid) {
+        return Err(e.into());
     }
+    eprintln!(
+        "warning: {} has no runtime scheme, using {} as a docker container id",
+        obj_path, raw_cid
+    );
+    Ok((ContainerRuntime::Docker, raw_cid.to_string()))
+}
+
+/// Check if the value looks like a container id, i.e. only letters and digits
+fn is_bare_container_id(val: &str) -> bool {
+    !val.is_empty() && val.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Extract the name, runtime and id of the ephemeral containers from the json of a pod
+///
+/// Only the running ephemeral containers are returned: the ones that were not started yet
+/// have no id and the terminated ones keep their id but have no process to enter
+pub fn parse_ephemeral_container_ids(
+    pod: &serde_json::Value,
+) -> Result<Vec<(Option<String>, ContainerRuntime, String)>, Error> {
+    let mut res = vec![];
+    if let Some(objs) = pod["status"]["ephemeralContainerStatuses"].as_array() {
+        for (idx, obj) in objs.iter().enumerate() {
+            let name = obj["name"].as_str().map(|s| s.to_string());
+            if obj["state"]["running"].is_null() {
+                debug!("ephemeral container {:?} is not running", name);
+                continue;
+            }
+            match obj["containerID"].as_str() {
+                Some(raw_cid) => {
+                    let obj_path =
+                        format!("pod.status.ephemeralContainerStatuses.{}.containerID", idx);
+                    let (runtime, id) = parse_container_id(raw_cid, obj_path)?;
+                    res.push((name, runtime, id));
+                }
+                None => debug!("ephemeral container {:?} was not started yet", name),
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// The container id schemes used by sandboxed runtimes
+const SANDBOXED_RUNTIME_SCHEMES: &[&str] = &["kata", "kata-runtime", "runsc", "gvisor"];
+
+/// Try to detect if the pod runs in a sandboxed runtime (e.g. kata or gVisor) using the
+/// annotations set by the runtimes
+///
+/// For these runtimes the container pid is a host-side process of the sandbox, not a normal
+/// container process, so its network namespace is not the one the container sees
+fn detect_sandboxed_runtime(pod: &kubeclient::resources::Pod) -> Option<String> {
+    let annotations = pod.metadata.annotations.as_ref()?;
+    for (key, val) in annotations {
+        if key.starts_with("io.katacontainers.") {
+            return Some("kata".to_string());
+        } else if key.starts_with("dev.gvisor.") {
+            return Some("gvisor".to_string());
+        } else if key == "io.kubernetes.cri.untrusted-workload" && val == "true" {
+            return Some("untrusted-workload".to_string());
+        }
+    }
+    None
 }
 
 /// Extract the IDs of the containers part of the given pod
-fn extract_container_info(pod: kubeclient::resources::Pod) -> Result<Vec<Container>, Error> {
+fn extract_container_info(
+    cfg: &Config,
+    pod: kubeclient::resources::Pod,
+) -> Result<Vec<Container>, Error> {
+    if let Some(runtime) = detect_sandboxed_runtime(&pod) {
+        Err(K8sError::SandboxedRuntime(runtime))?
+    }
+
     let mut res = vec![];
     match pod.status {
         Some(pod_status) => {
@@ -94,32 +597,25 @@ fn extract_container_info(pod: kubeclient::resources::Pod) -> Result<Vec<Contain
                         let obj_path = format!("pod.status.container_statuses.{}.containerID", idx);
                         let (runtime, container_id) =
                             match obj.get("containerID").and_then(|x| x.as_str()) {
-                                Some(raw_cid) => {
-                                    // the containerID is expected to have an URL format
-                                    // e.g. docker://c6671e7930e7181d7e..
-                                    let cid = Url::parse(raw_cid)?;
-
-                                    let runtime = match cid.scheme() {
-                                        "docker" => ContainerRuntime::Docker,
-                                        other @ _ => Err(K8sError::UnsupportedContainerRuntime(
-                                            other.to_string(),
-                                        ))?,
-                                    };
-
-                                    let id = match cid.host_str() {
-                                        Some(s) => s.to_string(),
-                                        None => Err(K8sError::UnsupportedFieldFormat {
-                                            field: obj_path,
-                                            val: raw_cid.to_string(),
-                                        })?,
-                                    };
-
-                                    (runtime, id)
-                                }
+                                Some(raw_cid) => parse_container_id(raw_cid, obj_path)?,
                                 None => Err(K8sError::MissingOrNullField(obj_path))?,
                             };
-                        let mut container = Container::new(container_id, runtime)?;
+                        let mut container = Container::new(cfg, container_id, runtime)?;
+                        container.container_name = obj
+                            .get("name")
+                            .and_then(|x| x.as_str())
+                            .map(|s| s.to_string());
                         container.node_name = pod.spec.node_name.clone();
+                        container.spec_host_network = pod.spec.host_network;
+                        container.networks_annotation = pod
+                            .metadata
+                            .annotations
+                            .as_ref()
+                            .and_then(|a| a.get(NETWORKS_ANNOTATION))
+                            .cloned();
+                        container.pod_name = pod.metadata.name.clone();
+                        container.namespace = pod.metadata.namespace.clone();
+                        container.pod_ip = pod_status.pod_id.map(|ip| ip.to_string());
                         res.push(container);
                     }
                 }
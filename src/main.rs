@@ -1,3 +1,4 @@
+extern crate chrono;
 extern crate docopt;
 extern crate env_logger;
 #[macro_use]
@@ -9,6 +10,7 @@ extern crate serde_derive;
 extern crate serde_json;
 #[macro_use]
 extern crate failure;
+extern crate flate2;
 extern crate kubeclient;
 extern crate regex;
 extern crate url;
@@ -19,14 +21,21 @@ extern crate tabwriter;
 // modules
 mod error;
 mod k8s;
+mod podman;
+mod selftest;
+#[cfg(feature = "http-server")]
+mod server;
 #[cfg(test)]
 mod tests;
 
 use docopt::Docopt;
 use failure::{Error, Fail, ResultExt};
+use flate2::write::GzEncoder;
 use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
 use std::io::Write;
 use std::process::Command;
+use std::time::{Duration, Instant};
 use tabwriter::TabWriter;
 
 include!(concat!(env!("OUT_DIR"), "/version.rs"));
@@ -36,42 +45,413 @@ fn version() -> String {
 }
 
 const USAGE: &'static str = "
-Usage: cniguru pod <id> [-n <namespace> ] [-o <output>]
-       cniguru dc <id> [-o <output> ]
+Usage: cniguru pod <id> [options] [-v...] [--exclude-interface <glob>]...
+       cniguru pods --select <selector> [options] [-v...] [--exclude-interface <glob>]...
+       cniguru pod-by-ip <ip> [options] [-v...] [--exclude-interface <glob>]...
+       cniguru dc <id> [options] [-v...] [--exclude-interface <glob>]...
+       cniguru whichpod <intf> [options] [-v...] [--exclude-interface <glob>]...
+       cniguru resolve-ip <ip> [options] [-v...] [--exclude-interface <glob>]...
+       cniguru --self [options] [-v...] [--exclude-interface <glob>]...
+       cniguru --from-file <path> [options] [-v...] [--exclude-interface <glob>]...
+       cniguru diff <report-a> <report-b> [options] [-v...]
+       cniguru exec (pod | dc) <id> [options] [-v...] [--] <cmd>...
+       cniguru serve [--listen <addr>] [options] [-v...] [--exclude-interface <glob>]...
+       cniguru selftest [options] [-v...]
        cniguru [-h] [--version]
 
 Options:
-    -h, --help         Show this message.
-    --version          Show the version
-    -n <namespace>     Specify a kubernetes namespace
-    -o <output>        Specify a different way to format the output, e.g. json
+    -h, --help              Show this message.
+    --version               Show the version
+    -v, --verbose           Log more details to stderr, can be repeated, -v for info, -vv for
+                            debug and -vvv for trace, $RUST_LOG takes precedence if set
+    -n <namespace>          Specify a kubernetes namespace
+    --select <selector>     Specify a kubernetes label selector, e.g. app=nginx
+    --wait <secs>           Wait up to <secs> seconds for the pod containers to be up and running
+    -o <output>             Specify a different way to format the output, json, ndjson,
+                            i.e. one result per line written as soon as it's ready, or
+                            iproute2, i.e. the container interfaces as `ip -j addr` lists them
+    --output-shape <shape>  The shape of the json output, array (the default) or map, i.e. an
+                            object keyed by <namespace>/<pod>/<container> for the pods and by
+                            container id otherwise
+    --exit-on-empty         Exit with code 2 if no interfaces were found
+    --self                  Inspect the container cniguru is running in, e.g. as a pod sidecar
+    --podman-socket <path>  Inspect a podman container with `dc`, its pid and labels are read
+                            from the Podman REST API listening on <path>, e.g.
+                            /run/podman/podman.sock, can also be set via $CNIGURU_PODMAN_SOCKET
+    --from-file <path>      Parse the veth interfaces from a saved `ip -d link show` or
+                            `ip addr show` printout instead of inspecting a container
+    --proc-path <dir>       The directory the host /proc is mounted on, e.g. /host/proc when
+                            running in a container, can also be set via $CNIGURU_PROC
+    --count-only            Only report the number of interfaces of every container and how
+                            many of them are up or down
+    --all-link-types        Also report the container links that are not Ethernet, e.g. ipip,
+                            sit or ppp, by default they are only counted
+    --pid-only              Only print the pid of the container(s), e.g. to be used with nsenter
+    --interface <glob>      Only report the interfaces with a container or node name matching
+                            the given pattern, `*` and `?` wildcards are supported
+    --exclude-interface <glob>
+                            Drop the interfaces with a container or node name matching the
+                            given pattern, can be repeated, applied after --interface
+    --profile <cni>         Check the primary interface of the containers is set up as done by
+                            the given CNI plugin, calico, flannel, cilium or multus, by default
+                            the plugin used by most of the containers is detected
+    --ifindex-churn         Report the number of links of the node and the highest ifindex in
+                            use, a large gap points to a node creating and deleting many links
+    --no-ipv6               Skip the IPv6 addresses, routes, neighbors and sysctls, e.g. for
+                            the IPv4-only clusters
+    --max-depth <n>         Follow at most <n> masters when resolving the master chain of the
+                            interfaces, 8 by default
+    --related               Also report the interfaces related to the ones matching the
+                            filter: their container master and link, e.g. a vlan parent,
+                            the ones on top of them and the ones sharing their node bridge
+    --capture-secs <n>      Count the packets/bytes seen by the container interfaces over <n>
+                            seconds using the deltas of their /proc/net/dev counters, no
+                            packets are captured, requires --interface
+    --ephemeral             Also inspect the ephemeral (debug) containers of the pod, needs
+                            kubectl
+    --with-netpol           Report the QoS class of the pods and the NetworkPolicies of their
+                            namespace selecting them, needs kubectl
+    --annotate-from-crd     Correlate the extra interfaces of Multus pods with the
+                            NetworkAttachmentDefinitions referenced by the pod, needs kubectl
+    --interfaces-from-spec  Report the networks requested by the Multus annotation of the pod
+                            that have no corresponding interface in the container
+    --sysctls               Report a set of networking sysctls, e.g. net.ipv4.ip_forward, read
+                            in the container network namespace
+    --extra-sysctls <keys>  Comma separated list of sysctls to report besides the default
+                            ones, requires --sysctls
+    --bridge-vlans          Report the VLANs, i.e. the PVID and the tagged and untagged ones,
+                            of the node interfaces plugged into a VLAN filtering bridge
+    --check-gateway         Report the default gateways of the container and the state of
+                            their neighbor (ARP/NDP) entry, e.g. REACHABLE or FAILED
+    --mac-oui-lookup        Annotate the MAC addresses with the vendor from a bundled table of
+                            prefixes, e.g. VMware or QEMU/KVM
+    --oui-db <path>         Use the MAC prefix to vendor table from <path> instead of the
+                            bundled one, requires --mac-oui-lookup
+    --output-file <path>    Write the json output to <path> instead of stdout, requires -o json
+                            or -o iproute2
+    --gzip                  Compress the output file with gzip, `.gz` is appended to <path>
+                            if missing, requires --output-file
+    --output-socket <path>  Write the json output to the unix socket at <path> instead of
+                            stdout, e.g. a local collector, requires -o json or -o iproute2
+    --report-missing        With `pods`, list the pods that can't be inspected in the json
+                            output as `found: false` entries and exit with code 1
+    --fail-fast             Stop at the first pod that can't be inspected when using `pods`,
+                            by default such pods are skipped and reported as errors
+    --timeout-per-target <secs>
+                            With `pods`, give up on a container whose inspection takes more
+                            than <secs> seconds, e.g. a wedged network namespace, and report
+                            it as a failed pod
+    --repeat <n>            Run the inspection of a pod or docker container <n> times and
+                            print the min/max/mean duration of every phase instead
+    --explain-host-network  Report why the container is considered to use, or not, the host
+                            network namespace
+    --listen <addr>         The address to serve on with `serve`, 127.0.0.1:8080 by default
+    --mount                 Also enter the mount namespace of the container with `exec`
+    --envelope              Wrap the json output in an object also holding the timestamp,
+                            the node name and the cniguru version
 
 Main commands:
-    pod                The name of a kubernetes pod
-    dc                 The name or id of a docker container
+    pod                     The name of a kubernetes pod
+    pods                    All the kubernetes pods matching a label selector
+    pod-by-ip               The IP address of a kubernetes pod, every pod with the address
+                            is inspected
+    dc                      The name or id of a docker container, or of a podman container
+                            with --podman-socket, or @<path> to read it from the given file
+    whichpod                The name of a node veth interface to find the owning pod/container for
+    resolve-ip              An IP address to find the owning pod/container and interface for
+    diff                    Compare two json reports, e.g. captured before and after a change
+    exec                    Run a command in the network namespace of a pod or docker container
+    serve                   Serve the inspection over HTTP, `GET /container/<id>` and
+                            `GET /pod/<namespace>/<name>` return the json output, requires
+                            cniguru to be built with the `http-server` feature
+    selftest                Check the environment cniguru needs, e.g. the capabilities, /proc,
+                            the network namespace of pid 1, docker and the kubernetes API,
+                            and exit with code 1 if any check fails
 ";
 
 #[derive(Debug, Deserialize)]
 struct Args {
     cmd_pod: bool,
+    cmd_pods: bool,
+    cmd_pod_by_ip: bool,
     cmd_dc: bool,
+    cmd_whichpod: bool,
+    cmd_resolve_ip: bool,
+    cmd_diff: bool,
+    cmd_exec: bool,
+    cmd_serve: bool,
+    cmd_selftest: bool,
     arg_id: String,
+    arg_cmd: Vec<String>,
+    arg_intf: String,
+    arg_ip: String,
+    arg_report_a: String,
+    arg_report_b: String,
     flag_n: Option<String>,
+    flag_select: Option<String>,
     flag_o: Option<OutputFormat>,
+    flag_output_shape: Option<OutputShape>,
+    flag_exit_on_empty: bool,
+    flag_self: bool,
+    flag_pid_only: bool,
+    flag_podman_socket: Option<String>,
+    flag_all_link_types: bool,
+    flag_count_only: bool,
+    flag_proc_path: Option<String>,
+    flag_from_file: Option<String>,
+    flag_interface: Option<String>,
+    flag_exclude_interface: Vec<String>,
+    flag_related: bool,
+    flag_max_depth: Option<usize>,
+    flag_no_ipv6: bool,
+    flag_ifindex_churn: bool,
+    flag_profile: Option<CniProfile>,
+    flag_ephemeral: bool,
+    flag_annotate_from_crd: bool,
+    flag_with_netpol: bool,
+    flag_interfaces_from_spec: bool,
+    flag_capture_secs: Option<u64>,
+    flag_envelope: bool,
+    flag_mount: bool,
+    flag_listen: Option<String>,
+    flag_fail_fast: bool,
+    flag_timeout_per_target: Option<u64>,
+    flag_report_missing: bool,
+    flag_explain_host_network: bool,
+    flag_repeat: Option<u64>,
+    flag_output_file: Option<String>,
+    flag_output_socket: Option<String>,
+    flag_gzip: bool,
+    flag_sysctls: bool,
+    flag_check_gateway: bool,
+    flag_bridge_vlans: bool,
+    flag_mac_oui_lookup: bool,
+    flag_oui_db: Option<String>,
+    flag_extra_sysctls: Option<String>,
+    flag_wait: Option<u64>,
     flag_version: bool,
+    flag_verbose: usize,
 }
 
+/// The settings needed deep down the call chain, e.g. by the container lookups
+///
+/// It is built once from the args, and the env variables they override, and passed
+/// along explicitly so nothing is shared through the process environment
+#[derive(Debug, Clone)]
+struct Config {
+    // the path to the Podman API socket, `None` to use docker for `dc`
+    podman_socket: Option<String>,
+    // the directory the host `/proc` is mounted on, without the trailing `/`
+    proc_path: String,
+    // the maximum number of masters followed when resolving a master chain
+    max_master_chain_len: usize,
+    // set with --no-ipv6 to skip the IPv6 queries in the container network namespaces
+    no_ipv6: bool,
+}
+
+impl Config {
+    fn new(args: &Args) -> Self {
+        Config {
+            podman_socket: args
+                .flag_podman_socket
+                .clone()
+                .or_else(|| std::env::var(PODMAN_SOCKET_ENV).ok()),
+            proc_path: args
+                .flag_proc_path
+                .clone()
+                .or_else(|| std::env::var(PROC_PATH_ENV).ok())
+                .map_or_else(
+                    || "/proc".to_string(),
+                    |v| v.trim_end_matches('/').to_string(),
+                ),
+            max_master_chain_len: args.flag_max_depth.unwrap_or(DEFAULT_MAX_MASTER_CHAIN_LEN),
+            no_ipv6: args.flag_no_ipv6,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            podman_socket: None,
+            proc_path: "/proc".to_string(),
+            max_master_chain_len: DEFAULT_MAX_MASTER_CHAIN_LEN,
+            no_ipv6: false,
+        }
+    }
+}
+
+/// The env variable holding the directory the host `/proc` is mounted on, --proc-path
+/// overrides it
+const PROC_PATH_ENV: &str = "CNIGURU_PROC";
+
+/// The exit code used by `--exit-on-empty` when no interfaces were found
+const EXIT_CODE_EMPTY: i32 = 2;
+
+/// The env variable holding the path to the Podman API socket, --podman-socket overrides it
+const PODMAN_SOCKET_ENV: &str = "CNIGURU_PODMAN_SOCKET";
+
+/// The exit code used for errors and when every target of a best-effort run failed
+const EXIT_CODE_ERROR: i32 = 1;
+
+/// The version of the json output format
+///
+/// Fields may be added within a version but renaming or removing any of them requires
+/// a bump, so the consumers of the reports can detect the changes
+const OUTPUT_VERSION: u32 = 2;
+
+/// The bundled MAC prefix to vendor table used by `--mac-oui-lookup`
+const OUI_DB: &str = include_str!("oui.txt");
+
+/// The address `serve` listens on if --listen is not used
+const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:8080";
+
+/// The name of the interface the CNI plugins set up in every pod
+const PRIMARY_INTF: &str = "eth0";
+
+/// The bridge the flannel CNI plugin plugs the host veths into
+const FLANNEL_BRIDGE: &str = "cni0";
+
+/// The sysctls reported by `--sysctls`, the usual suspects when a pod can't route
+const DEFAULT_SYSCTLS: &[&str] = &[
+    "net.ipv4.ip_forward",
+    "net.ipv4.conf.all.rp_filter",
+    "net.ipv4.conf.default.rp_filter",
+    "net.ipv6.conf.all.forwarding",
+];
+
 #[derive(Debug, Deserialize)]
 enum OutputFormat {
     JSON,
+    // one json document per line, streamed as the pods are inspected
+    Ndjson,
+    // the container interfaces in the `ip -j link/addr show` schema
+    Iproute2,
 }
 
-fn main() {
-    env_logger::init();
+#[derive(Debug, Deserialize)]
+enum OutputShape {
+    Array,
+    Map,
+}
+
+/// The CNI plugins whose setup of the pod interfaces is known, see --profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+enum CniProfile {
+    Calico,
+    Flannel,
+    Cilium,
+    Multus,
+}
+
+impl CniProfile {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            CniProfile::Calico => "calico",
+            CniProfile::Flannel => "flannel",
+            CniProfile::Cilium => "cilium",
+            CniProfile::Multus => "multus",
+        }
+    }
+
+    /// Guess the CNI plugin that set up the container from the node side of its primary
+    /// interface, e.g. calico names the host veths `cali*`, or from the Multus annotation
+    fn guess(output: &Output) -> Option<CniProfile> {
+        if output.container.networks_annotation.is_some() {
+            return Some(CniProfile::Multus);
+        }
+        let node = output.primary_node_intf()?;
+        if node.name.starts_with("cali") {
+            Some(CniProfile::Calico)
+        } else if node.name.starts_with("lxc") {
+            Some(CniProfile::Cilium)
+        } else if node.bridge.as_ref().map(|b| &b[..]) == Some(FLANNEL_BRIDGE) {
+            Some(CniProfile::Flannel)
+        } else {
+            None
+        }
+    }
 
+    /// Detect the CNI plugin used by most of the containers, so the other ones stand out
+    fn detect(output: &[Output]) -> Option<CniProfile> {
+        let mut counts: Vec<(CniProfile, usize)> = vec![];
+        for profile in output.iter().filter_map(CniProfile::guess) {
+            match counts.iter_mut().find(|(p, _)| *p == profile) {
+                Some(c) => c.1 += 1,
+                None => counts.push((profile, 1)),
+            }
+        }
+        // the first one seen wins a tie
+        let max = counts.iter().map(|(_, n)| *n).max()?;
+        counts.into_iter().find(|(_, n)| *n == max).map(|(p, _)| p)
+    }
+
+    /// Check the primary interface of the container is set up as done by the CNI plugin
+    ///
+    /// The Multus networks are checked separately, see `check_requested_networks`
+    fn check(&self, output: &mut Output) {
+        let (prefix, bridge) = match *self {
+            CniProfile::Calico => (Some("cali"), None),
+            CniProfile::Cilium => (Some("lxc"), None),
+            CniProfile::Flannel => (None, Some(FLANNEL_BRIDGE)),
+            CniProfile::Multus => return,
+        };
+        let mut problems = vec![];
+        if let Some(node) = output.primary_node_intf() {
+            if let Some(prefix) = prefix {
+                if !node.name.starts_with(prefix) {
+                    problems.push(format!(
+                        "{} (node) is not named {}* as done by {}",
+                        node.name,
+                        prefix,
+                        self.as_str()
+                    ));
+                }
+            }
+            let node_bridge = node.bridge.as_ref().map(|b| &b[..]);
+            if node_bridge != bridge {
+                problems.push(format!(
+                    "{} (node) is plugged into {} while {} uses {}",
+                    node.name,
+                    node_bridge.unwrap_or("no bridge"),
+                    self.as_str(),
+                    bridge.unwrap_or("no bridge")
+                ));
+            }
+        }
+        for msg in problems {
+            output
+                .problems
+                .push(Problem::warning("cni-profile-mismatch", msg));
+        }
+    }
+}
+
+/// The log level set by repeating `-v`, `None` to keep the env_logger default
+fn verbosity_level(count: usize) -> Option<log::LevelFilter> {
+    match count {
+        0 => None,
+        1 => Some(log::LevelFilter::Info),
+        2 => Some(log::LevelFilter::Debug),
+        _ => Some(log::LevelFilter::Trace),
+    }
+}
+
+fn init_logger(verbose: usize) {
+    // `RUST_LOG` overrides the level set via `-v`
+    match verbosity_level(verbose) {
+        Some(level) if std::env::var_os("RUST_LOG").is_none() => {
+            env_logger::Builder::new().filter_level(level).init()
+        }
+        _ => env_logger::init(),
+    }
+}
+
+fn main() {
     let args: Args = Docopt::new(USAGE)
         .and_then(|d| d.deserialize())
         .unwrap_or_else(|e| e.exit());
+    init_logger(args.flag_verbose);
     debug!("program args: {:?}", args);
 
     if args.flag_version {
@@ -79,180 +459,2489 @@ fn main() {
         return;
     }
 
-    match try_main(&args) {
-        Ok(v) => match args.flag_o {
-            Some(OutputFormat::JSON) => println!(
-                "{}",
-                serde_json::to_string_pretty(&v).expect("failed to serialize the output to json")
-            ),
-            None => pretty_print_output_and_exit(v),
-        },
+    let cfg = Config::new(&args);
+    let mut errors = vec![];
+    match try_main(&args, &cfg, &mut errors) {
+        Ok(v) => {
+            for e in errors.iter() {
+                eprintln!("warning: skipping {}: {}", e.target, e.error);
+            }
+            let found = v.iter().any(|o| !o.interfaces.is_empty());
+            let code = exit_code(&args, !v.is_empty(), found, &errors);
+            if args.flag_count_only {
+                print_counts_and_exit(&v, args.flag_o.is_some(), code);
+            }
+            match args.flag_o {
+                Some(OutputFormat::Ndjson) => {
+                    print_ndjson(&v, &errors, args.flag_report_missing);
+                    std::process::exit(code);
+                }
+                Some(ref fmt) => {
+                    let s = if let OutputFormat::Iproute2 = *fmt {
+                        serde_json::to_string_pretty(&iproute2_links(&v))
+                    } else if args.flag_envelope {
+                        serde_json::to_string_pretty(&Envelope::new(&v, &errors))
+                    } else if let Some(OutputShape::Map) = args.flag_output_shape {
+                        match outputs_by_key(&v) {
+                            Ok(m) => serde_json::to_string_pretty(&m),
+                            Err(e) => return print_err_as_json_and_exit(e),
+                        }
+                    } else if args.flag_report_missing {
+                        let mut entries: Vec<serde_json::Value> = v
+                            .iter()
+                            .map(serde_json::to_value)
+                            .collect::<Result<_, _>>()
+                            .expect("failed to serialize the output to json");
+                        entries.extend(errors.iter().map(|e| e.missing_entry()));
+                        serde_json::to_string_pretty(&entries)
+                    } else {
+                        serde_json::to_string_pretty(&v)
+                    };
+                    let s = s.expect("failed to serialize the output to json");
+                    let res = match (&args.flag_output_file, &args.flag_output_socket) {
+                        (Some(path), _) => write_output_file(path, &s, args.flag_gzip),
+                        (None, Some(path)) => write_output_socket(path, &s),
+                        (None, None) => {
+                            println!("{}", s);
+                            Ok(())
+                        }
+                    };
+                    if let Err(e) = res {
+                        print_err_as_json_and_exit(e);
+                    }
+                    std::process::exit(code);
+                }
+                None => pretty_print_output_and_exit(v, code),
+            }
+        }
         Err(e) => match args.flag_o {
-            Some(OutputFormat::JSON) => print_err_as_json_and_exit(e),
+            Some(_) => print_err_as_json_and_exit(e),
             None => pretty_print_err_and_exit(e),
         },
     }
 }
 
+/// The exit code of a run given if it had any results, if any of them had interfaces
+/// and the targets skipped in best-effort mode
+fn exit_code(args: &Args, results: bool, interfaces: bool, errors: &[TargetError]) -> i32 {
+    if (!results || args.flag_report_missing) && !errors.is_empty() {
+        EXIT_CODE_ERROR
+    } else if args.flag_exit_on_empty && !interfaces {
+        EXIT_CODE_EMPTY
+    } else {
+        0
+    }
+}
+
+/// Print the results and the errors as one json document per line, the errors as
+/// `"found": false` entries with `missing`
+fn print_ndjson(output: &[Output], errors: &[TargetError], missing: bool) {
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    for o in output {
+        let s = serde_json::to_string(o).expect("failed to serialize the output to json");
+        writeln!(out, "{}", s).expect("could not write to stdout");
+    }
+    for e in errors {
+        let s = if missing {
+            serde_json::to_string(&e.missing_entry())
+        } else {
+            serde_json::to_string(e)
+        };
+        let s = s.expect("failed to serialize the output to json");
+        writeln!(out, "{}", s).expect("could not write to stdout");
+    }
+    out.flush().expect("could not write to stdout");
+}
+
+/// Get the container interfaces of every result in the `ip -j addr show` schema, so the
+/// tools parsing the iproute2 json can be used unchanged
+fn iproute2_links(output: &[Output]) -> Vec<serde_json::Value> {
+    output
+        .iter()
+        .flat_map(|o| o.interfaces.iter())
+        .map(|p| p.container.to_iproute2())
+        .collect()
+}
+
 /// Wrapper on top of `main()` to be able to use `?` for error handling
-fn try_main(args: &Args) -> Result<Vec<Output>, Error> {
+///
+/// The errors for the targets skipped in best-effort mode are added to `errors`
+fn try_main(
+    args: &Args,
+    cfg: &Config,
+    errors: &mut Vec<TargetError>,
+) -> Result<Vec<Output>, Error> {
     let mut output_vec = vec![];
 
+    if args.flag_capture_secs.is_some() && args.flag_interface.is_none() {
+        Err(error::InvalidArgsErr("--capture-secs requires --interface"))?
+    }
+
+    // the pods found by IP address are running already and are inspected like with `pods`
+    if args.cmd_pod_by_ip && (args.flag_ephemeral || args.flag_wait.is_some()) {
+        Err(error::InvalidArgsErr(
+            "--ephemeral and --wait are not supported with pod-by-ip",
+        ))?
+    }
+
+    if args.flag_related && args.flag_interface.is_none() {
+        Err(error::InvalidArgsErr("--related requires --interface"))?
+    }
+
+    if args.flag_extra_sysctls.is_some() && !args.flag_sysctls {
+        Err(error::InvalidArgsErr("--extra-sysctls requires --sysctls"))?
+    }
+
+    if args.flag_output_file.is_some() && args.flag_o.is_none() {
+        Err(error::InvalidArgsErr(
+            "--output-file requires -o json or -o iproute2",
+        ))?
+    }
+
+    if args.flag_output_socket.is_some() {
+        if args.flag_o.is_none() {
+            Err(error::InvalidArgsErr(
+                "--output-socket requires -o json or -o iproute2",
+            ))?
+        }
+        if args.flag_output_file.is_some() {
+            Err(error::InvalidArgsErr(
+                "--output-socket is not supported with --output-file",
+            ))?
+        }
+    }
+
+    if let Some(OutputFormat::Ndjson) = args.flag_o {
+        if args.flag_envelope {
+            Err(error::InvalidArgsErr(
+                "--envelope is not supported with -o ndjson",
+            ))?
+        }
+        // the lines are printed as the pods are inspected so nothing is left to count or
+        // to write elsewhere at the end
+        if args.flag_count_only
+            || args.flag_output_file.is_some()
+            || args.flag_output_socket.is_some()
+        {
+            Err(error::InvalidArgsErr(
+                "--count-only, --output-file and --output-socket are not supported with -o ndjson",
+            ))?
+        }
+    }
+
+    if let Some(OutputFormat::Iproute2) = args.flag_o {
+        if args.flag_envelope || args.flag_report_missing {
+            Err(error::InvalidArgsErr(
+                "-o iproute2 is not supported with --envelope or --report-missing",
+            ))?
+        }
+    }
+
+    if let Some(OutputShape::Map) = args.flag_output_shape {
+        match args.flag_o {
+            Some(OutputFormat::JSON) if !args.flag_envelope => (),
+            _ => Err(error::InvalidArgsErr(
+                "--output-shape map requires -o json and is not supported with --envelope",
+            ))?,
+        }
+    }
+
+    if args.flag_report_missing {
+        if let Some(OutputShape::Map) = args.flag_output_shape {
+            Err(error::InvalidArgsErr(
+                "--report-missing is not supported with --output-shape map",
+            ))?
+        }
+    }
+
+    if args.flag_oui_db.is_some() && !args.flag_mac_oui_lookup {
+        Err(error::InvalidArgsErr("--oui-db requires --mac-oui-lookup"))?
+    }
+
+    if args.flag_gzip && args.flag_output_file.is_none() {
+        Err(error::InvalidArgsErr("--gzip requires --output-file"))?
+    }
+
+    if args.flag_ifindex_churn && args.flag_self {
+        Err(error::InvalidArgsErr(
+            "--ifindex-churn is not supported with --self",
+        ))?
+    }
+
+    if args.flag_mount && !args.cmd_exec {
+        Err(error::InvalidArgsErr("--mount requires exec"))?
+    }
+
+    if args.cmd_selftest {
+        let json = matches!(args.flag_o, Some(OutputFormat::JSON));
+        selftest::print_and_exit(&selftest::run(cfg), json);
+    }
+
+    if args.cmd_serve {
+        let addr = args
+            .flag_listen
+            .as_ref()
+            .map_or(DEFAULT_LISTEN_ADDR, |x| &x[..]);
+        serve(args, cfg, addr)?;
+        return Ok(output_vec);
+    }
+
+    // `exec` shares the pod/dc arguments so it must be handled before them
+    if args.cmd_exec {
+        let container = if args.cmd_pod {
+            let pod = k8s::Pod::new(&args.arg_id, args.flag_n.as_ref().map(|x| &x[..]));
+            let err_ctx = format!(
+                "failed to get info about containers in pod '{}' on namespace '{}'",
+                pod.name, pod.namespace
+            );
+            // every container of the pod shares the network namespace, use the first one running
+            match pod
+                .containers(cfg)
+                .context(err_ctx)?
+                .into_iter()
+                .find(|c| c.pid != 0)
+            {
+                Some(c) => c,
+                None => Err(error::NoRunningContainerErr(pod.name.to_string()))?,
+            }
+        } else {
+            Container::new(cfg, read_container_id(&args.arg_id)?, dc_runtime(cfg))?
+        };
+        Err(exec_in_container(
+            cfg,
+            &container,
+            &args.arg_cmd,
+            args.flag_mount,
+        ))?
+    }
+
+    if args.flag_max_depth == Some(0) {
+        Err(error::InvalidArgsErr("--max-depth must be at least 1"))?
+    }
+
+    if args.flag_timeout_per_target == Some(0) {
+        Err(error::InvalidArgsErr(
+            "--timeout-per-target must be at least 1",
+        ))?
+    }
+
+    if let Some(n) = args.flag_repeat {
+        if n == 0 {
+            Err(error::InvalidArgsErr("--repeat must be at least 1"))?
+        }
+        if args.cmd_pod {
+            let pod = k8s::Pod::new(&args.arg_id, args.flag_n.as_ref().map(|x| &x[..]));
+            print_timings(cfg, n, || pod.containers(cfg))?;
+        } else if args.cmd_dc {
+            print_timings(cfg, n, || {
+                Ok(vec![Container::new(
+                    cfg,
+                    read_container_id(&args.arg_id)?,
+                    dc_runtime(cfg),
+                )?])
+            })?;
+        } else {
+            Err(error::InvalidArgsErr(
+                "--repeat is only supported for pod and dc",
+            ))?
+        }
+        std::process::exit(0);
+    }
+
     if args.cmd_pod {
         let pod = k8s::Pod::new(&args.arg_id, args.flag_n.as_ref().map(|x| &x[..]));
         let err_ctx = format!(
             "failed to get info about containers in pod '{}' on namespace '{}'",
             pod.name, pod.namespace
         );
-        let containers = pod.containers().context(err_ctx)?;
+        let mut containers = match args.flag_wait {
+            Some(secs) => pod.wait_for_containers(cfg, Duration::from_secs(secs)),
+            None => pod.containers(cfg),
+        }
+        .context(err_ctx)?;
+        if args.flag_ephemeral && !containers.is_empty() {
+            let ephemeral = pod
+                .ephemeral_containers(cfg, &containers[0])
+                .context(format!(
+                    "failed to get the ephemeral containers in pod '{}'",
+                    pod.name
+                ))?;
+            containers.extend(ephemeral);
+        }
+        if args.flag_pid_only {
+            print_pids_and_exit(&containers);
+        }
         for container in containers {
-            let output = gen_output_for_container(container)?;
+            let output = gen_output_for_container(cfg, container)?;
             output_vec.push(output);
         }
+    } else if args.cmd_pods || args.cmd_pod_by_ip {
+        let namespace = args.flag_n.as_ref().map(|x| &x[..]);
+        let found = if args.cmd_pods {
+            let selector = args
+                .flag_select
+                .as_ref()
+                .map(|x| &x[..])
+                .unwrap_or_default();
+            let pods = k8s::PodSelector::new(selector, namespace);
+            let err_ctx = format!(
+                "failed to list the pods matching '{}' on namespace '{}'",
+                pods.selector, pods.namespace
+            );
+            pods.containers(cfg).context(err_ctx)?
+        } else {
+            let pods = k8s::PodsByIp::new(parse_ip_arg(&args.arg_ip)?, namespace);
+            let err_ctx = format!(
+                "failed to find the pods with IP address '{}' on namespace '{}'",
+                pods.ip, pods.namespace
+            );
+            // the pods sharing the address, e.g. using the host network, are all reported
+            pods.containers(cfg).context(err_ctx)?
+        };
+        // unless --fail-fast is used, a pod that can't be inspected is reported and skipped
+        let mut pod_containers = vec![];
+        for (name, res) in found {
+            match res {
+                Ok(c) => pod_containers.push((name, c)),
+                Err(e) => skip_target(format!("pod '{}'", name), e, args.flag_fail_fast, errors)?,
+            }
+        }
+        if args.flag_pid_only {
+            let containers: Vec<Container> =
+                pod_containers.into_iter().flat_map(|(_, c)| c).collect();
+            print_pids_and_exit(&containers);
+        }
+        // with ndjson every pod is printed as soon as it is inspected instead of buffering
+        // all of them, the run ends here
+        let stream = matches!(args.flag_o, Some(OutputFormat::Ndjson));
+        let (mut results, mut interfaces) = (false, false);
+        for (name, containers) in pod_containers {
+            let res: Result<Vec<Output>, Error> = containers
+                .into_iter()
+                .map(|c| match args.flag_timeout_per_target {
+                    Some(secs) => {
+                        let cfg = cfg.clone();
+                        run_with_timeout(Duration::from_secs(secs), move || {
+                            gen_output_for_container(&cfg, c)
+                        })
+                    }
+                    None => gen_output_for_container(cfg, c),
+                })
+                .collect();
+            // when streaming, a pod failing the post-processing is skipped like the pods
+            // that could not be inspected instead of ending the stream
+            let res = if stream {
+                res.and_then(|mut outputs| post_process(args, cfg, &mut outputs).map(|_| outputs))
+            } else {
+                res
+            };
+            match res {
+                Ok(outputs) if stream => {
+                    results |= !outputs.is_empty();
+                    interfaces |= outputs.iter().any(|o| !o.interfaces.is_empty());
+                    print_ndjson(&outputs, &[], false);
+                }
+                Ok(outputs) => output_vec.extend(outputs),
+                Err(e) => {
+                    skip_target(format!("pod '{}'", name), e, args.flag_fail_fast, errors)?;
+                    if stream {
+                        let e = errors.last().expect("the error was just recorded");
+                        eprintln!("warning: skipping {}: {}", e.target, e.error);
+                        print_ndjson(&[], std::slice::from_ref(e), args.flag_report_missing);
+                    }
+                }
+            }
+        }
+        if stream {
+            std::process::exit(exit_code(args, results, interfaces, errors));
+        }
     } else if args.cmd_dc {
-        let container = Container::new(args.arg_id.clone(), ContainerRuntime::Docker)?;
-        let output = gen_output_for_container(container)?;
+        let container = Container::new(cfg, read_container_id(&args.arg_id)?, dc_runtime(cfg))?;
+        if args.flag_pid_only {
+            print_pids_and_exit(std::slice::from_ref(&container));
+        }
+        let output = gen_output_for_container(cfg, container)?;
         output_vec.push(output);
+    } else if args.cmd_whichpod {
+        let err_ctx = format!(
+            "failed to find the containers using node interface '{}'",
+            &args.arg_intf
+        );
+        let outputs = find_containers_by_node_intf(cfg, &args.arg_intf).context(err_ctx)?;
+        output_vec.extend(outputs);
+    } else if args.cmd_resolve_ip {
+        let ip = parse_ip_arg(&args.arg_ip)?;
+        let err_ctx = format!(
+            "failed to find the containers with IP address '{}'",
+            &args.arg_ip
+        );
+        let outputs = find_containers_by_ip(cfg, ip).context(err_ctx)?;
+        output_vec.extend(outputs);
+    } else if args.flag_self {
+        let container = Container::new_self(cfg)?;
+        // the node side of the veth pairs is not visible from inside the container
+        let interfaces = container
+            .get_container_interfaces(cfg)?
+            .into_iter()
+            .map(|cintf| VethIntfPair {
+                container: cintf,
+                node: None,
+                traffic: None,
+                related: false,
+                network: None,
+                bridge_vlans: None,
+            })
+            .collect();
+        output_vec.push(Output::new(container, interfaces));
+    } else if args.cmd_diff {
+        let a = load_report(&args.arg_report_a)?;
+        let b = load_report(&args.arg_report_b)?;
+        print_diff_and_exit(&diff_reports(&a, &b), args.flag_o.is_some());
+    } else if let Some(ref path) = args.flag_from_file {
+        let content = std::fs::read_to_string(path).context(format!("failed to read {}", path))?;
+        let mut intfs = parse_ip_link_or_addr_printout(&content, cfg.max_master_chain_len)?;
+        if !args.flag_all_link_types {
+            intfs.retain(|i| i.is_ether());
+        }
+        if let Some(ref pattern) = args.flag_interface {
+            let re = glob_to_regex(pattern)?;
+            intfs.retain(|i| i.name_matches(&re));
+        }
+        let exclude = exclude_regexes(args)?;
+        intfs.retain(|i| !exclude.iter().any(|re| i.name_matches(re)));
+        print_interfaces_and_exit(&intfs, args.flag_o.is_some());
     } else {
         println!("Not enough arguments.\n{}", &USAGE);
         std::process::exit(1);
     }
+
+    post_process(args, cfg, &mut output_vec)?;
+
     Ok(output_vec)
 }
 
-/// Generate the `Output` struct for the given container
-fn gen_output_for_container(container: Container) -> Result<Output, Error> {
-    let ctx = format!(
-        "failed to generate the output interface pairs for container id {}",
-        &container.id
-    );
-    let interfaces = container.interfaces().context(ctx)?;
-    Ok(Output {
-        container,
-        interfaces,
+/// Complete the outputs as requested by the args, e.g. filter the interfaces or read
+/// the sysctls
+fn post_process(args: &Args, cfg: &Config, output_vec: &mut [Output]) -> Result<(), Error> {
+    if !args.flag_all_link_types {
+        for output in output_vec.iter_mut() {
+            let count = output.interfaces.len();
+            output.interfaces.retain(|p| p.container.is_ether());
+            output.skipped_interfaces = count - output.interfaces.len();
+            debug!(
+                "skipped {} non-Ethernet links of container {}",
+                output.skipped_interfaces, output.container.id
+            );
+        }
+    }
+
+    // done before filtering so all the interfaces the networks refer to are present
+    if args.flag_annotate_from_crd {
+        for output in output_vec.iter_mut() {
+            annotate_networks(output);
+        }
+    }
+
+    let profile = match args.flag_profile {
+        Some(p) => Some(p),
+        None => CniProfile::detect(output_vec),
+    };
+    debug!("using the CNI profile {:?}", profile);
+    let multus = profile == Some(CniProfile::Multus);
+
+    if args.flag_annotate_from_crd || args.flag_interfaces_from_spec || multus {
+        for output in output_vec.iter_mut() {
+            check_requested_networks(output);
+        }
+    }
+
+    if let Some(profile) = profile {
+        for output in output_vec.iter_mut() {
+            profile.check(output);
+        }
+    }
+
+    if args.flag_with_netpol {
+        annotate_policies(output_vec);
+    }
+
+    let include = match args.flag_interface {
+        Some(ref pattern) => Some(glob_to_regex(pattern)?),
+        None => None,
+    };
+    let exclude = exclude_regexes(args)?;
+    for output in output_vec.iter_mut() {
+        filter_interfaces(
+            &mut output.interfaces,
+            include.as_ref(),
+            &exclude,
+            args.flag_related,
+        );
+    }
+
+    if let Some(secs) = args.flag_capture_secs {
+        capture_traffic(cfg, output_vec, secs)?;
+    }
+
+    if args.flag_explain_host_network {
+        for output in output_vec.iter_mut() {
+            output.container.host_network_basis = output.container.explain_host_network(cfg);
+        }
+    }
+
+    if args.flag_sysctls {
+        let mut keys: Vec<&str> = DEFAULT_SYSCTLS
+            .iter()
+            .cloned()
+            .filter(|k| !(cfg.no_ipv6 && k.starts_with("net.ipv6.")))
+            .collect();
+        if let Some(ref extra) = args.flag_extra_sysctls {
+            keys.extend(extra.split(',').map(|k| k.trim()).filter(|k| !k.is_empty()));
+        }
+        for output in output_vec.iter_mut() {
+            output.sysctls = Some(output.container.get_sysctls(cfg, &keys)?);
+        }
+    }
+
+    if args.flag_mac_oui_lookup {
+        let db = match args.flag_oui_db {
+            Some(ref path) => parse_oui_db(
+                &std::fs::read_to_string(path).context(format!("failed to read {}", path))?,
+            ),
+            None => parse_oui_db(OUI_DB),
+        };
+        for output in output_vec.iter_mut() {
+            for pair in output.interfaces.iter_mut() {
+                for intf in std::iter::once(&mut pair.container).chain(pair.node.as_mut()) {
+                    intf.vendor = lookup_vendor(&db, &intf.mac_address);
+                }
+            }
+        }
+    }
+
+    if args.flag_check_gateway {
+        for output in output_vec.iter_mut() {
+            let gateways = output.container.get_gateways(cfg)?;
+            if gateways.is_empty() {
+                output.problems.push(Problem::warning(
+                    "no-default-route",
+                    "no default route via a gateway".to_string(),
+                ));
+            }
+            for gw in gateways.iter().filter(|gw| !gw.is_resolved()) {
+                output.problems.push(Problem::error(
+                    "gateway-unresolved",
+                    format!(
+                        "default gateway {} is not resolved, neighbor state: {}",
+                        gw.address,
+                        gw.state.as_ref().map_or("absent", |s| &s[..])
+                    ),
+                ));
+            }
+            output.gateways = Some(gateways);
+        }
+    }
+
+    // the node side of the pairs is on the host so the VLANs are listed only once
+    if args.flag_bridge_vlans && !output_vec.is_empty() {
+        debug!("fetching node `ip -d link show type bridge` and `bridge vlan show` printouts");
+        let bridges = parse_vlan_filtering_bridges(&run_host_cmd("ip -d link show type bridge")?);
+        let vlans = parse_bridge_vlans(&run_host_cmd("bridge vlan show")?);
+        for output in output_vec.iter_mut() {
+            for pair in output.interfaces.iter_mut() {
+                let port = match pair.node {
+                    Some(ref n) if bridges.iter().any(|b| n.bridge.as_ref() == Some(b)) => &n.name,
+                    _ => continue,
+                };
+                pair.bridge_vlans = vlans.get(port).cloned();
+            }
+        }
+    }
+
+    // the node is the same for every result so its links are listed only once
+    if args.flag_ifindex_churn && !output_vec.is_empty() {
+        debug!("fetching node `ip -o link show` printout");
+        let churn = parse_ifindex_churn(&run_host_cmd("ip -o link show")?);
+        for output in output_vec.iter_mut() {
+            output.ifindex_churn = churn.clone();
+        }
+    }
+
+    Ok(())
+}
+
+/// Count the links in an `ip -o link show` printout and find the highest ifindex
+///
+/// The kernel does not reuse the ifindex of the deleted links right away so the gap between
+/// the two is roughly the number of links deleted since the node booted
+fn parse_ifindex_churn(printout: &str) -> Option<IfindexChurn> {
+    let indexes: Vec<u32> = printout
+        .lines()
+        .filter_map(|l| l.split(':').next())
+        .filter_map(|v| v.trim().parse().ok())
+        .collect();
+    let max_ifindex = *indexes.iter().max()?;
+    Some(IfindexChurn {
+        links: indexes.len(),
+        max_ifindex,
+        gap: max_ifindex.saturating_sub(indexes.len() as u32),
     })
 }
 
-/// Pretty print the error and exit with code `1`
-fn pretty_print_err_and_exit(e: Error) {
-    let mut fail: &Fail = e.cause();
-    let mut f = std::io::stderr();
-    write!(std::io::stderr(), "error: {}\n", fail).expect("could not write to stderr");
-    while let Some(cause) = fail.cause() {
-        write!(f, "caused by: {}\n", cause).expect("could not write to stderr");
-        fail = cause;
+/// Return the container id given as argument or, if it has the `@<path>` format, the one
+/// read from the file
+///
+/// The runtime scheme, e.g. `docker://`, is removed as in the kubernetes pod status
+fn read_container_id(arg: &str) -> Result<String, Error> {
+    let (id, path) = if let Some(path) = arg.strip_prefix('@') {
+        let id = std::fs::read_to_string(path)
+            .context(format!("failed to read the container id from {}", path))?;
+        (id, Some(path))
+    } else {
+        (arg.to_string(), None)
+    };
+    let id = id.trim();
+    let id = match id.find("://") {
+        Some(pos) => &id[pos + 3..],
+        None => id,
+    };
+    // an empty id would only make `docker inspect` fail with a confusing error
+    if id.is_empty() {
+        let err = error::InvalidArgsErr("the container id is empty");
+        match path {
+            Some(path) => Err(err).context(format!("no container id in {}", path))?,
+            None => Err(err)?,
+        }
+    }
+    Ok(id.to_string())
+}
+
+/// Run the inspection `n` times and print the min/max/mean duration of every phase
+///
+/// `lookup` finds the containers to inspect and their pid, e.g. using the kubernetes API
+fn print_timings<F>(cfg: &Config, n: u64, lookup: F) -> Result<(), Error>
+where
+    F: Fn() -> Result<Vec<Container>, Error>,
+{
+    let mut timings: Vec<(&str, Vec<Duration>)> = vec![
+        ("pid lookup", vec![]),
+        ("container link fetch", vec![]),
+        ("node link fetch", vec![]),
+    ];
+    for _ in 0..n {
+        let start = Instant::now();
+        let containers = lookup()?;
+        timings[0].1.push(start.elapsed());
+
+        for container in containers.iter() {
+            let start = Instant::now();
+            container.get_container_interfaces(cfg)?;
+            timings[1].1.push(start.elapsed());
+        }
+
+        let start = Instant::now();
+        get_node_interfaces(cfg)?;
+        timings[2].1.push(start.elapsed());
+    }
+
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    let mut r = vec!["PHASE\tRUNS\tMIN(ms)\tMAX(ms)\tMEAN(ms)".to_string()];
+    for (phase, durations) in timings {
+        let (min, max) = match (durations.iter().min(), durations.iter().max()) {
+            (Some(min), Some(max)) => (*min, *max),
+            _ => continue,
+        };
+        let mean = durations.iter().sum::<Duration>() / durations.len() as u32;
+        r.push(format!(
+            "{}\t{}\t{:.3}\t{:.3}\t{:.3}",
+            phase,
+            durations.len(),
+            ms(min),
+            ms(max),
+            ms(mean)
+        ));
+    }
+    let tw = TabWriter::new(Vec::<u8>::new());
+    println!("\n{}\n", tabify(tw, &r.join("\n"))?);
+    Ok(())
+}
+
+/// Handle the error hit for one of the targets of a multi-target run
+///
+/// With `fail_fast` the error is returned so the whole run is aborted, otherwise it is
+/// recorded in `errors` and the run goes on with the next target
+fn skip_target(
+    target: String,
+    e: Error,
+    fail_fast: bool,
+    errors: &mut Vec<TargetError>,
+) -> Result<(), Error> {
+    if fail_fast {
+        return Err(e.context(format!("failed to inspect {}", target)).into());
+    }
+    errors.push(TargetError::new(target, &e));
+    Ok(())
+}
+
+/// Attach the Multus networks referenced by the pod to the corresponding interfaces, along
+/// with the CNI config from their `NetworkAttachmentDefinition`
+fn annotate_networks(output: &mut Output) {
+    let val = match output.container.networks_annotation {
+        Some(ref v) => v.clone(),
+        None => return,
+    };
+    let namespace = output
+        .container
+        .namespace
+        .clone()
+        .unwrap_or_else(|| "default".to_string());
+    // an invalid annotation is reported by `check_requested_networks()`
+    let nets = match k8s::parse_networks_annotation(&val, &namespace) {
+        Ok(v) => v,
+        Err(e) => {
+            debug!(
+                "skipping the networks of container {}: {}",
+                output.container.id, e
+            );
+            return;
+        }
+    };
+    for mut net in nets {
+        match k8s::get_network_attachment_config(&net.namespace, &net.name) {
+            Ok(config) => net.config = config,
+            Err(e) => output.problems.push(Problem::warning(
+                "nad-fetch-failed",
+                format!(
+                    "failed to fetch NetworkAttachmentDefinition {}/{}: {}",
+                    net.namespace, net.name, e
+                ),
+            )),
+        }
+        // the missing interfaces are reported by `check_requested_networks()`
+        if let Some(pair) = output
+            .interfaces
+            .iter_mut()
+            .find(|p| p.container.name == net.interface)
+        {
+            pair.network = Some(net);
+        }
+    }
+}
+
+/// Fill in the QoS class of the pods and the NetworkPolicies selecting them
+///
+/// The pods are looked up once even if several of their containers are inspected, a
+/// lookup that fails is reported as a problem of every container of the pod
+fn annotate_policies(output_vec: &mut [Output]) {
+    let mut cache: HashMap<(String, String), Result<k8s::PodPolicies, String>> = HashMap::new();
+    for output in output_vec.iter_mut() {
+        let key = match (&output.container.namespace, &output.container.pod_name) {
+            (Some(ns), Some(name)) => (ns.clone(), name.clone()),
+            _ => continue,
+        };
+        let res = cache
+            .entry(key)
+            .or_insert_with_key(|k| k8s::get_pod_policies(&k.0, &k.1).map_err(|e| e.to_string()));
+        match *res {
+            Ok(ref v) => {
+                output.container.qos_class = v.qos_class.clone();
+                output.container.network_policies = Some(v.network_policies.clone());
+            }
+            Err(ref e) => output.problems.push(Problem::warning(
+                "netpol-fetch-failed",
+                format!("failed to fetch the NetworkPolicies of the pod: {}", e),
+            )),
+        }
+    }
+}
+
+/// Cross-check the networks requested by the Multus annotation of the pod with the
+/// interfaces of the container and report the ones that were not attached
+///
+/// The annotation is set by the pod author so an invalid one is reported as a problem
+/// of the container instead of failing the whole run
+fn check_requested_networks(output: &mut Output) {
+    let val = match output.container.networks_annotation {
+        Some(ref v) => v.clone(),
+        None => return,
+    };
+    let namespace = output
+        .container
+        .namespace
+        .clone()
+        .unwrap_or_else(|| "default".to_string());
+    let nets = match k8s::parse_networks_annotation(&val, &namespace) {
+        Ok(v) => v,
+        Err(e) => {
+            output
+                .problems
+                .push(Problem::error("networks-annotation-invalid", e.to_string()));
+            return;
+        }
+    };
+    for net in nets {
+        if !output
+            .interfaces
+            .iter()
+            .any(|p| p.container.name == net.interface)
+        {
+            output.problems.push(Problem::error(
+                "network-missing",
+                format!(
+                    "requested network {}/{} has no interface {} in the container",
+                    net.namespace, net.name, net.interface
+                ),
+            ));
+        }
+    }
+}
+
+/// Compile the patterns given with --exclude-interface
+fn exclude_regexes(args: &Args) -> Result<Vec<Regex>, Error> {
+    args.flag_exclude_interface
+        .iter()
+        .map(|p| glob_to_regex(p))
+        .collect()
+}
+
+/// Keep the interface pairs matching `include`, and the related ones if requested, then
+/// drop the ones matching any of the `exclude` patterns
+fn filter_interfaces(
+    pairs: &mut Vec<VethIntfPair>,
+    include: Option<&Regex>,
+    exclude: &[Regex],
+    related: bool,
+) {
+    if let Some(re) = include {
+        if related {
+            mark_related(pairs, re);
+        }
+        pairs.retain(|p| p.related || p.name_matches(re));
+    }
+    pairs.retain(|p| !exclude.iter().any(|re| p.name_matches(re)));
+}
+
+/// Mark the interface pairs related to the ones matching the regex, i.e.:
+/// - the pairs plugged into a matching node bridge or into the same node bridge as a
+///   matching pair
+/// - the pairs whose container interface is the master or the link of a matching one,
+///   e.g. the bridge of a port or the parent of a vlan, or is on top of a matching one
+///
+/// The veth peer needs no special handling as both ends are part of the pair. The masters
+/// on the node are not part of any pair, they are reported in the master chain of the node
+/// interfaces
+fn mark_related(pairs: &mut [VethIntfPair], re: &Regex) {
+    let bridges: Vec<String> = pairs
+        .iter()
+        .filter(|p| p.name_matches(re))
+        .filter_map(|p| p.bridge().map(|b| b.to_string()))
+        .collect();
+    let names: Vec<String> = pairs
+        .iter()
+        .filter(|p| p.name_matches(re))
+        .map(|p| p.container.name.clone())
+        .collect();
+    let uppers: Vec<String> = pairs
+        .iter()
+        .filter(|p| p.name_matches(re))
+        .flat_map(|p| p.container.bridge.iter().chain(p.container.link.iter()))
+        .cloned()
+        .collect();
+    // the matching pairs are reported as matched, not as related
+    for p in pairs.iter_mut() {
+        let by_bridge = match p.bridge() {
+            Some(b) => re.is_match(b) || bridges.iter().any(|x| x == b),
+            None => false,
+        };
+        let is_upper = uppers.contains(&p.container.name);
+        let is_lower = p
+            .container
+            .bridge
+            .iter()
+            .chain(p.container.link.iter())
+            .any(|x| names.contains(x));
+        p.related = !p.name_matches(re) && (by_bridge || is_upper || is_lower);
+    }
+}
+
+/// Convert a shell-like pattern supporting the `*` and `?` wildcards to an anchored regex
+fn glob_to_regex(pattern: &str) -> Result<Regex, Error> {
+    let s = regex::escape(pattern)
+        .replace(r"\*", ".*")
+        .replace(r"\?", ".");
+    Ok(Regex::new(&format!("^{}$", s))?)
+}
+
+/// Fill in the traffic seen by the container interfaces over the given number of seconds
+///
+/// No packets are captured, the numbers are the deltas of the kernel interface counters
+///
+/// The counters of all the containers are read before a single sleep so the total run time
+/// does not depend on the number of containers
+fn capture_traffic(cfg: &Config, output: &mut [Output], secs: u64) -> Result<(), Error> {
+    let mut before = vec![];
+    for o in output.iter() {
+        before.push(o.container.get_netdev_stats(cfg)?);
+    }
+
+    debug!("capturing traffic for {} seconds", secs);
+    std::thread::sleep(Duration::from_secs(secs));
+
+    for (o, before) in output.iter_mut().zip(before) {
+        let after = o.container.get_netdev_stats(cfg)?;
+        for pair in o.interfaces.iter_mut() {
+            let name = &pair.container.name;
+            if let (Some(b), Some(a)) = (before.get(name), after.get(name)) {
+                pair.traffic = Some(TrafficSample {
+                    secs,
+                    rx_packets: a.rx_packets.saturating_sub(b.rx_packets),
+                    rx_bytes: a.rx_bytes.saturating_sub(b.rx_bytes),
+                    tx_packets: a.tx_packets.saturating_sub(b.tx_packets),
+                    tx_bytes: a.tx_bytes.saturating_sub(b.tx_bytes),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Convert a sysctl key, e.g. `net.ipv4.ip_forward`, to its path under `/proc/sys`
+///
+/// Only the keys under `net` are accepted as the others are not specific to the namespace
+fn sysctl_key_to_path(key: &str) -> Result<String, Error> {
+    lazy_static! {
+        static ref SYSCTL_KEY_RE: Regex = Regex::new(r"^net(\.[\w-]+)+$").unwrap();
+    }
+    if !SYSCTL_KEY_RE.is_match(key) {
+        Err(error::InvalidArgsErr(
+            "sysctl keys must be of the form `net.<...>`",
+        ))?
+    }
+    Ok(format!("/proc/sys/{}", key.replace('.', "/")))
+}
+
+/// Generate the `Output` struct for the given container
+fn gen_output_for_container(cfg: &Config, mut container: Container) -> Result<Output, Error> {
+    let ctx = format!(
+        "failed to generate the output interface pairs for container id {}",
+        &container.id
+    );
+    container.host_network = container.shares_host_ns(cfg, "net");
+    container.host_pid = container.shares_host_ns(cfg, "pid");
+    container.host_ipc = container.shares_host_ns(cfg, "ipc");
+    container.netns = netns_id(cfg, container.pid);
+    let interfaces = container.interfaces(cfg).context(ctx)?;
+    Ok(Output::new(container, interfaces))
+}
+
+/// Run `f` in a separate thread and give up on it if it does not finish within `timeout`
+///
+/// The thread is left behind, along with any command it is waiting for, so this is only
+/// meant to keep a stuck target from blocking the rest of the run
+fn run_with_timeout<T, F>(timeout: Duration, f: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, Error> + Send + 'static,
+{
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        // the receiver is gone if the timeout expired
+        let _ = tx.send(f());
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(res) => res,
+        Err(_) => Err(error::TargetTimeoutErr(timeout.as_secs()))?,
+    }
+}
+
+#[cfg(feature = "http-server")]
+fn serve(args: &Args, cfg: &Config, addr: &str) -> Result<(), Error> {
+    server::serve(args, cfg, addr)
+}
+
+#[cfg(not(feature = "http-server"))]
+fn serve(_args: &Args, _cfg: &Config, _addr: &str) -> Result<(), Error> {
+    Err(error::InvalidArgsErr(
+        "serve requires cniguru to be built with the http-server feature",
+    ))?
+}
+
+/// Apply `f` to every item using up to `max` threads at a time, keeping the order of the items
+fn map_concurrently<T, R, F>(items: Vec<T>, max: usize, f: F) -> Vec<R>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    let f = std::sync::Arc::new(f);
+    let mut res = Vec::with_capacity(items.len());
+    let mut items = items.into_iter().peekable();
+    while items.peek().is_some() {
+        let handles: Vec<_> = items
+            .by_ref()
+            .take(max)
+            .map(|item| {
+                let f = f.clone();
+                std::thread::spawn(move || f(item))
+            })
+            .collect();
+        for h in handles {
+            res.push(h.join().expect("a worker thread panicked"));
+        }
+    }
+    res
+}
+
+/// List the ids of the running containers of the runtime used for `dc`, see `dc_runtime()`
+fn running_container_ids(cfg: &Config) -> Result<Vec<String>, Error> {
+    match cfg.podman_socket {
+        Some(ref socket) => podman::list_containers(socket),
+        None => {
+            debug!("fetching the ids of the running docker containers");
+            let output = run_host_cmd("docker ps -q --no-trunc")?;
+            Ok(output.lines().map(|s| s.to_string()).collect())
+        }
+    }
+}
+
+/// Check every running container and return the ones having an interface for which `pred`
+/// is true, along with that interface
+///
+/// The containers of a pod share the network namespace so the interfaces of every
+/// namespace are fetched only once, keyed by the namespace inode. The containers that can't
+/// be inspected, e.g. the ones that exited meanwhile, are skipped, and so are the ones
+/// using the host network if `skip_host_network` is set
+fn scan_containers<P>(
+    cfg: &Config,
+    skip_host_network: bool,
+    pred: P,
+) -> Result<Vec<(Container, VethIntf)>, Error>
+where
+    P: Fn(&VethIntf) -> bool,
+{
+    let find = |container: &Container| {
+        // containers without the interfaces, e.g. without a network namespace, are skipped
+        let cintfs = match container.get_container_interfaces(cfg) {
+            Ok(v) => v,
+            Err(e) => {
+                debug!("skipping container {}: {}", &container.id, e);
+                return None;
+            }
+        };
+        cintfs.into_iter().find(|c| pred(c))
+    };
+
+    let mut netns_intfs: HashMap<std::path::PathBuf, Option<VethIntf>> = HashMap::new();
+
+    let mut res = vec![];
+    for id in running_container_ids(cfg)? {
+        let mut container = match Container::new(cfg, id.clone(), dc_runtime(cfg)) {
+            Ok(c) => c,
+            Err(e) => {
+                debug!("skipping container {}: {}", id, e);
+                continue;
+            }
+        };
+        if skip_host_network && container.shares_host_ns(cfg, "net") == Some(true) {
+            continue;
+        }
+        let cintf = match read_ns_link(cfg, container.pid, "net") {
+            Some(netns) => netns_intfs
+                .entry(netns)
+                .or_insert_with(|| find(&container))
+                .clone(),
+            None => find(&container),
+        };
+        if let Some(cintf) = cintf {
+            // the container is still reported if the pod it belongs to can't be found
+            if let Err(e) = container.load_pod_info(cfg) {
+                eprintln!(
+                    "warning: failed to fetch the pod info of container {}: {}",
+                    container.id, e
+                );
+            }
+            res.push((container, cintf));
+        }
+    }
+    Ok(res)
+}
+
+/// Find the containers whose network namespace holds the peer of the given node interface
+///
+/// All the running containers are checked so, for a kubernetes pod, both the pause
+/// container and the containers sharing its network namespace are returned
+fn find_containers_by_node_intf(cfg: &Config, name: &str) -> Result<Vec<Output>, Error> {
+    debug!("fetching node `ip -d link show {}` printout", name);
+    let cmd = format!("ip -d link show {}", name);
+    let output = run_host_cmd(&cmd)?;
+    let nintf = match parse_ip_link_or_addr_printout(&output, cfg.max_master_chain_len)?.pop() {
+        Some(v) => v,
+        None => Err(error::IpLinkOrAddrShowParseErr)?,
+    };
+
+    // the ifindex is only unique within a namespace so both ends of the pair are checked
+    let res: Vec<Output> = scan_containers(cfg, false, |c| {
+        c.ifindex == nintf.peer_ifindex && c.peer_ifindex == nintf.ifindex
+    })?
+    .into_iter()
+    .map(|(container, cintf)| {
+        let pair = VethIntfPair {
+            container: cintf,
+            node: Some(nintf.clone()),
+            traffic: None,
+            related: false,
+            network: None,
+            bridge_vlans: None,
+        };
+        Output::new(container, vec![pair])
+    })
+    .collect();
+
+    if res.is_empty() {
+        Err(error::IntfOwnerMissingErr(name.to_string()))?
+    } else {
+        Ok(res)
+    }
+}
+
+/// Parse the `<ip>` argument as an IPv4 or IPv6 address
+fn parse_ip_arg(ip: &str) -> Result<std::net::IpAddr, Error> {
+    match ip.parse() {
+        Ok(v) => Ok(v),
+        Err(_) => Err(error::InvalidArgsErr(
+            "<ip> must be an IPv4 or IPv6 address",
+        ))?,
+    }
+}
+
+/// Find the containers whose network namespace has the given IP address configured
+///
+/// The containers using the host network are skipped since they share the node addresses
+fn find_containers_by_ip(cfg: &Config, ip: std::net::IpAddr) -> Result<Vec<Output>, Error> {
+    let node_intfs = get_node_interfaces(cfg)?;
+
+    let res: Vec<Output> = scan_containers(cfg, true, |c| c.has_address(&ip))?
+        .into_iter()
+        .map(|(container, cintf)| {
+            let nintf = if cintf.is_ether() {
+                node_intfs
+                    .iter()
+                    .find(|n| n.ifindex == cintf.peer_ifindex)
+                    .cloned()
+            } else {
+                None
+            };
+            let pair = VethIntfPair {
+                container: cintf,
+                node: nintf,
+                traffic: None,
+                related: false,
+                network: None,
+                bridge_vlans: None,
+            };
+            Output::new(container, vec![pair])
+        })
+        .collect();
+
+    if res.is_empty() {
+        Err(error::IpOwnerMissingErr(ip.to_string()))?
+    } else {
+        Ok(res)
+    }
+}
+
+/// Print the pid of every container, one per line, and exit with code `0`
+fn print_pids_and_exit(containers: &[Container]) -> ! {
+    for container in containers {
+        println!("{}", container.pid);
+    }
+    std::process::exit(0);
+}
+
+/// Pretty print the error and exit with code `1`
+fn pretty_print_err_and_exit(e: Error) {
+    let mut fail: &Fail = e.cause();
+    let mut f = std::io::stderr();
+    write!(std::io::stderr(), "error: {}\n", fail).expect("could not write to stderr");
+    while let Some(cause) = fail.cause() {
+        write!(f, "caused by: {}\n", cause).expect("could not write to stderr");
+        fail = cause;
+    }
+    if std::env::var("RUST_BACKTRACE").is_ok() {
+        write!(f, "{}\n", e.backtrace()).expect("could not write to stderr");
+    }
+    std::process::exit(1);
+}
+
+/// Print the error in JSON format and exit with code `1`
+fn print_err_as_json_and_exit(e: Error) {
+    let mut fail: &Fail = e.cause();
+    let mut caused_by = vec![];
+    while let Some(cause) = fail.cause() {
+        caused_by.push(cause.to_string());
+        fail = cause;
+    }
+
+    let err_str = json!({
+        "error": e.cause().to_string(),
+        "caused_by": caused_by
+    });
+
+    println!("{}", err_str);
+    std::process::exit(1);
+}
+
+/// Write the serialized output to the given file, optionally gzip compressed
+///
+/// The content is written to a temporary file in the same directory which is then renamed
+/// so readers never see a partially written report
+fn write_output_file(path: &str, content: &str, gzip: bool) -> Result<(), Error> {
+    let path = if gzip && !path.ends_with(".gz") {
+        format!("{}.gz", path)
+    } else {
+        path.to_string()
+    };
+    let tmp_path = format!("{}.tmp", path);
+    debug!("writing the output to {}", path);
+
+    let file = std::fs::File::create(&tmp_path)?;
+    if gzip {
+        let mut enc = GzEncoder::new(file, flate2::Compression::default());
+        enc.write_all(content.as_bytes())?;
+        enc.finish()?.sync_all()?;
+    } else {
+        let mut file = file;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+    }
+    std::fs::rename(&tmp_path, &path)?;
+    Ok(())
+}
+
+/// Send the output to a collector listening on the unix socket at `path`
+///
+/// The write side of the connection is shut down once the output is written so the
+/// collector can read until EOF
+fn write_output_socket(path: &str, content: &str) -> Result<(), Error> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixStream;
+
+    match std::fs::metadata(path) {
+        Ok(ref m) if m.file_type().is_socket() => (),
+        _ => Err(error::OutputSocketMissingErr(path.to_string()))?,
+    }
+    debug!("writing the output to unix socket {}", path);
+    let mut stream = UnixStream::connect(path).context(format!("failed to connect to {}", path))?;
+    stream.write_all(content.as_bytes())?;
+    stream.write_all(b"\n")?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+    Ok(())
+}
+
+/// Key the results by `<namespace>/<pod>/<container>` or, if the container is not part
+/// of a pod, by container id
+///
+/// The same key showing up twice is an error as the results would be lost otherwise
+fn outputs_by_key(output: &[Output]) -> Result<BTreeMap<String, &Output>, Error> {
+    let mut res = BTreeMap::new();
+    for o in output {
+        let c = &o.container;
+        let key = match (c.namespace.as_ref(), c.pod_name.as_ref()) {
+            (Some(ns), Some(pod)) => format!(
+                "{}/{}/{}",
+                ns,
+                pod,
+                c.container_name.as_ref().unwrap_or(&c.id)
+            ),
+            _ => c.id.clone(),
+        };
+        if res.contains_key(&key) {
+            return Err(error::DuplicateKeyErr(key).into());
+        }
+        res.insert(key, o);
+    }
+    Ok(res)
+}
+
+/// Load a json report saved with `-o json`, with or without `--envelope` or
+/// `--output-shape map`
+fn load_report(path: &str) -> Result<Vec<Output>, Error> {
+    let content = std::fs::read_to_string(path).context(format!("failed to read {}", path))?;
+    let mut v: serde_json::Value =
+        serde_json::from_str(&content).context(format!("failed to parse {}", path))?;
+    if let Some(results) = v.get_mut("results") {
+        v = results.take();
+    }
+    // saved with `--output-shape map`
+    if let serde_json::Value::Object(m) = v {
+        v = serde_json::Value::Array(m.into_iter().map(|(_, o)| o).collect());
+    }
+    // the targets that were not found, listed with `--report-missing`
+    if let Some(entries) = v.as_array_mut() {
+        entries.retain(|o| o.get("found") != Some(&serde_json::Value::Bool(false)));
+    }
+    let report: Vec<Output> =
+        serde_json::from_value(v).context(format!("failed to load the report {}", path))?;
+    check_report_version(&report).context(format!("failed to load the report {}", path))?;
+    Ok(report)
+}
+
+/// Make sure the report was not saved by a newer cniguru with a different output format
+fn check_report_version(report: &[Output]) -> Result<(), error::UnsupportedOutputVersionErr> {
+    match report.iter().find(|o| o.output_version > OUTPUT_VERSION) {
+        Some(o) => Err(error::UnsupportedOutputVersionErr(o.output_version)),
+        None => Ok(()),
+    }
+}
+
+/// Compare two reports and describe what changed, per container and per interface
+fn diff_reports(a: &[Output], b: &[Output]) -> Vec<String> {
+    let mut res = vec![];
+    for oa in a {
+        let short_id = short_id(&oa.container.id);
+        let ob = match b.iter().find(|o| o.container.id == oa.container.id) {
+            Some(o) => o,
+            None => {
+                res.push(format!("container {}: removed", short_id));
+                continue;
+            }
+        };
+        for pa in oa.interfaces.iter() {
+            let name = &pa.container.name;
+            let pb = match ob.interfaces.iter().find(|p| &p.container.name == name) {
+                Some(p) => p,
+                None => {
+                    res.push(format!(
+                        "container {}: interface {} removed",
+                        short_id, name
+                    ));
+                    continue;
+                }
+            };
+            let (ca, cb) = (&pa.container, &pb.container);
+            let mut changes = vec![];
+            if ca.mtu != cb.mtu {
+                changes.push(format!("mtu {} -> {}", ca.mtu, cb.mtu));
+            }
+            if ca.is_up() != cb.is_up() {
+                let state = |up| if up { "UP" } else { "DOWN" };
+                changes.push(format!(
+                    "state {} -> {}",
+                    state(ca.is_up()),
+                    state(cb.is_up())
+                ));
+            }
+            if ca.ip_address != cb.ip_address {
+                changes.push(format!(
+                    "ip {} -> {}",
+                    ca.ip_address.as_ref().map_or("-", |s| &s[..]),
+                    cb.ip_address.as_ref().map_or("-", |s| &s[..])
+                ));
+            }
+            if ca.mac_address != cb.mac_address {
+                changes.push(format!("mac {} -> {}", ca.mac_address, cb.mac_address));
+            }
+            let (na, nb) = (
+                pa.node.as_ref().map(|n| &n.name[..]),
+                pb.node.as_ref().map(|n| &n.name[..]),
+            );
+            if na != nb {
+                changes.push(format!(
+                    "node interface {} -> {}",
+                    na.unwrap_or("-"),
+                    nb.unwrap_or("-")
+                ));
+            }
+            if pa.bridge() != pb.bridge() {
+                changes.push(format!(
+                    "bridge {} -> {}",
+                    pa.bridge().unwrap_or("-"),
+                    pb.bridge().unwrap_or("-")
+                ));
+            }
+            for c in changes {
+                res.push(format!("container {}: {}: {}", short_id, name, c));
+            }
+        }
+        for pb in ob.interfaces.iter() {
+            let name = &pb.container.name;
+            if !oa.interfaces.iter().any(|p| &p.container.name == name) {
+                res.push(format!("container {}: interface {} added", short_id, name));
+            }
+        }
+    }
+    for ob in b {
+        if !a.iter().any(|o| o.container.id == ob.container.id) {
+            res.push(format!("container {}: added", short_id(&ob.container.id)));
+        }
+    }
+    res
+}
+
+/// Print the differences between two reports, as json or one per line, and exit
+fn print_diff_and_exit(changes: &[String], json: bool) -> ! {
+    if json {
+        let s =
+            serde_json::to_string_pretty(changes).expect("failed to serialize the output to json");
+        println!("{}", s);
+    } else if changes.is_empty() {
+        println!("no changes");
+    } else {
+        for c in changes {
+            println!("{}", c);
+        }
+    }
+    std::process::exit(0);
+}
+
+/// Print the interfaces parsed from a file, as json or in a human readable format, and exit
+fn print_interfaces_and_exit(intfs: &[VethIntf], json: bool) -> ! {
+    if json {
+        let s =
+            serde_json::to_string_pretty(intfs).expect("failed to serialize the output to json");
+        println!("{}", s);
+        std::process::exit(0);
+    }
+
+    let mut r =
+        vec!["INTF\tIFINDEX\tPEER_IFINDEX\tMTU\tMAC_ADDRESS\tIP_ADDRESS\tBRIDGE".to_string()];
+    for i in intfs {
+        r.push(format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            i.name,
+            i.ifindex,
+            i.peer_ifindex,
+            i.mtu,
+            i.mac_address,
+            i.ip_address.as_ref().map_or("-", |s| &s[..]),
+            i.bridge.as_ref().map_or("-", |s| &s[..])
+        ));
+    }
+    let tw = TabWriter::new(Vec::<u8>::new());
+    println!(
+        "\n{}\n",
+        tabify(tw, &r.join("\n")).expect("failed to format the output")
+    );
+    std::process::exit(0);
+}
+
+/// The number of interfaces of a container, reported by --count-only
+#[derive(Debug, Serialize)]
+struct IntfCount<'a> {
+    id: &'a str,
+    pod: Option<String>,
+    interfaces: usize,
+    up: usize,
+    down: usize,
+}
+
+impl<'a> IntfCount<'a> {
+    fn new(output: &'a Output) -> Self {
+        let c = &output.container;
+        let up = output
+            .interfaces
+            .iter()
+            .filter(|p| p.container.is_up())
+            .count();
+        Self {
+            id: &c.id,
+            pod: match (c.namespace.as_ref(), c.pod_name.as_ref()) {
+                (Some(ns), Some(name)) => Some(format!("{}/{}", ns, name)),
+                _ => None,
+            },
+            interfaces: output.interfaces.len(),
+            up,
+            down: output.interfaces.len() - up,
+        }
+    }
+}
+
+/// Print the number of interfaces of every container, as json or in a human readable format,
+/// and exit with the given code
+fn print_counts_and_exit(output: &[Output], json: bool, code: i32) -> ! {
+    let counts: Vec<IntfCount> = output.iter().map(IntfCount::new).collect();
+    if json {
+        let s =
+            serde_json::to_string_pretty(&counts).expect("failed to serialize the output to json");
+        println!("{}", s);
+        std::process::exit(code);
+    }
+
+    let mut r = vec!["CONTAINER_ID\tPOD\tINTERFACES\tUP\tDOWN".to_string()];
+    for c in counts {
+        r.push(format!(
+            "{}\t{}\t{}\t{}\t{}",
+            short_id(c.id),
+            c.pod.as_ref().map_or("-", |s| &s[..]),
+            c.interfaces,
+            c.up,
+            c.down
+        ));
+    }
+    let tw = TabWriter::new(Vec::<u8>::new());
+    println!(
+        "\n{}\n",
+        tabify(tw, &r.join("\n")).expect("failed to format the output")
+    );
+    std::process::exit(code);
+}
+
+/// The first 12 characters of a container id, as `docker ps` shows them
+///
+/// The id can be shorter, e.g. a container name given to `dc`, or come from a report file
+fn short_id(id: &str) -> &str {
+    id.get(..12).unwrap_or(id)
+}
+
+/// Pretty print the output and exit with the given code
+fn pretty_print_output_and_exit(output: Vec<Output>, code: i32) {
+    let mut r = vec![];
+
+    let problems: Vec<String> = output
+        .iter()
+        .flat_map(|o| {
+            o.problems.iter().map(move |p| {
+                format!(
+                    "{}: container {}: {} [{}]",
+                    p.severity.as_str(),
+                    short_id(&o.container.id),
+                    p.message,
+                    p.code
+                )
+            })
+        })
+        .collect();
+
+    let notes: Vec<String> = output
+        .iter()
+        .filter_map(|o| {
+            o.container
+                .host_network_basis
+                .as_ref()
+                .map(|b| format!("container {}: {}", short_id(&o.container.id), b))
+        })
+        .chain(output.iter().filter(|o| o.skipped_interfaces > 0).map(|o| {
+            format!(
+                "container {}: {} non-Ethernet links not reported, use --all-link-types",
+                short_id(&o.container.id),
+                o.skipped_interfaces
+            )
+        }))
+        .chain(
+            output
+                .iter()
+                .filter_map(|o| o.ifindex_churn.as_ref())
+                .take(1)
+                .map(|c| {
+                    format!(
+                        "node: {} links, highest ifindex {}, about {} links deleted since boot",
+                        c.links, c.max_ifindex, c.gap
+                    )
+                }),
+        )
+        .collect();
+
+    // the sysctls are shown in a separate table as they are per container, not per interface
+    let mut sysctls = vec![];
+    for o in output.iter() {
+        for sysctl in o.sysctls.iter().flat_map(|v| v.iter()) {
+            if sysctls.is_empty() {
+                sysctls.push("CONTAINER_ID\tSYSCTL\tVALUE".to_string());
+            }
+            sysctls.push(format!(
+                "{}\t{}\t{}",
+                short_id(&o.container.id),
+                sysctl.key,
+                sysctl.value.as_ref().map_or("-", |s| &s[..])
+            ));
+        }
+    }
+
+    // same for the default gateways
+    let mut gateways = vec![];
+    for o in output.iter() {
+        for gw in o.gateways.iter().flat_map(|v| v.iter()) {
+            if gateways.is_empty() {
+                gateways.push("CONTAINER_ID\tGATEWAY\tDEV\tNEIGHBOR_STATE".to_string());
+            }
+            gateways.push(format!(
+                "{}\t{}\t{}\t{}",
+                short_id(&o.container.id),
+                gw.address,
+                gw.dev.as_ref().map_or("-", |s| &s[..]),
+                gw.state.as_ref().map_or("-", |s| &s[..])
+            ));
+        }
+    }
+
+    // the related column is only shown if --related pulled in any interface
+    let with_related = output
+        .iter()
+        .any(|o| o.interfaces.iter().any(|p| p.related));
+
+    // the traffic columns are only shown if a capture was done
+    let with_traffic = output
+        .iter()
+        .any(|o| o.interfaces.iter().any(|p| p.traffic.is_some()));
+
+    if output.len() > 0 {
+        let mut l =
+            "CONTAINER_ID\tNAME\tPID\tNODE\tPOD\tINTF(C)\tMAC_ADDRESS(C)\tIP_ADDRESS(C)\tINTF(N)\tBRIDGE(N)"
+                .to_string();
+        if with_traffic {
+            l.push_str("\tRX_PKTS/BYTES(C)\tTX_PKTS/BYTES(C)");
+        }
+        if with_related {
+            l.push_str("\tRELATED");
+        }
+        r.push(l);
+    }
+
+    for i in output {
+        let short_id = short_id(&i.container.id);
+        let pod = match (
+            i.container.namespace.as_ref(),
+            i.container.pod_name.as_ref(),
+        ) {
+            (Some(ns), Some(name)) => format!("{}/{}", ns, name),
+            _ => "-".to_string(),
+        };
+        let name = match i.container.container_name {
+            Some(ref n) if i.container.ephemeral => format!("{} (ephemeral)", n),
+            Some(ref n) => n.to_string(),
+            None => "-".to_string(),
+        };
+        for intf in i.interfaces {
+            let mut l = format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                short_id,
+                &name,
+                i.container.pid,
+                i.container.node_name.as_ref().map_or("-", |s| &s[..]),
+                &pod,
+                &intf.container.name,
+                &intf.container.mac_address,
+                &intf.container.ip_address.as_ref().map_or("-", |s| &s[..]),
+                intf.node.as_ref().map_or("-", |n| &n.name[..]),
+                intf.node
+                    .as_ref()
+                    .and_then(|n| n.bridge.as_ref())
+                    .map_or("-", |s| &s[..])
+            );
+            if with_traffic {
+                let t = match intf.traffic {
+                    Some(ref t) => format!(
+                        "\t{}/{}\t{}/{}",
+                        t.rx_packets, t.rx_bytes, t.tx_packets, t.tx_bytes
+                    ),
+                    None => "\t-\t-".to_string(),
+                };
+                l.push_str(&t);
+            }
+            if with_related {
+                l.push_str(if intf.related { "\tyes" } else { "\tno" });
+            }
+            r.push(l);
+        }
+    }
+
+    let output_string = r.join("\n");
+    let tw = TabWriter::new(Vec::<u8>::new());
+    println!(
+        "\n{}\n",
+        tabify(tw, &output_string[..]).expect("failed to format the output")
+    );
+    if !sysctls.is_empty() {
+        let tw = TabWriter::new(Vec::<u8>::new());
+        println!(
+            "{}\n",
+            tabify(tw, &sysctls.join("\n")).expect("failed to format the output")
+        );
+    }
+    if !gateways.is_empty() {
+        let tw = TabWriter::new(Vec::<u8>::new());
+        println!(
+            "{}\n",
+            tabify(tw, &gateways.join("\n")).expect("failed to format the output")
+        );
+    }
+    for p in problems {
+        eprintln!("{}", p);
+    }
+    for n in notes {
+        eprintln!("note: {}", n);
+    }
+    std::process::exit(code);
+}
+
+/// Align the tab separated values to make them look nice
+pub fn tabify(mut tw: TabWriter<Vec<u8>>, s: &str) -> Result<String, Error> {
+    write!(&mut tw, "{}", s)?;
+    tw.flush()?;
+    Ok(String::from_utf8(tw.into_inner()?)?)
+}
+
+/// The output data structure
+///
+/// It is also deserialized to compare saved reports, the fields added over time default
+/// to empty values so older reports can still be loaded
+#[derive(Debug, Serialize, Deserialize)]
+struct Output {
+    // missing from the reports saved before the output was versioned
+    #[serde(default)]
+    output_version: u32,
+    container: Container,
+    interfaces: Vec<VethIntfPair>,
+    #[serde(default)]
+    problems: Vec<Problem>,
+    sysctls: Option<Vec<Sysctl>>,
+    // set with --check-gateway
+    gateways: Option<Vec<Gateway>>,
+    #[serde(default)]
+    summary: IntfSummary,
+    // the number of non-Ethernet container links left out, see --all-link-types
+    #[serde(default)]
+    skipped_interfaces: usize,
+    // set with --ifindex-churn
+    #[serde(default)]
+    ifindex_churn: Option<IfindexChurn>,
+}
+
+/// The links of the node compared to the highest ifindex in use, see --ifindex-churn
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct IfindexChurn {
+    links: usize,
+    max_ifindex: u32,
+    // the ifindexes below the highest one not in use, i.e. the links deleted since boot
+    gap: u32,
+}
+
+/// How bad a problem found in the container networking is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// An anomaly found in the container networking, e.g. a MTU mismatch on a veth pair
+///
+/// The code identifies the check, e.g. `mtu-mismatch`, so automation does not have to
+/// rely on the wording of the message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Problem {
+    code: String,
+    severity: Severity,
+    message: String,
+}
+
+impl Problem {
+    fn error(code: &str, message: String) -> Self {
+        Self {
+            code: code.to_string(),
+            severity: Severity::Error,
+            message,
+        }
+    }
+
+    fn warning(code: &str, message: String) -> Self {
+        Self {
+            code: code.to_string(),
+            severity: Severity::Warning,
+            message,
+        }
+    }
+}
+
+/// The number of container interfaces per kind, e.g. `veth`, and per operational state
+///
+/// It covers all the interfaces of the container, including the ones left out by `--interface`
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct IntfSummary {
+    kinds: BTreeMap<String, usize>,
+    oper_states: BTreeMap<String, usize>,
+}
+
+impl IntfSummary {
+    fn new(interfaces: &[VethIntfPair]) -> Self {
+        let mut summary = Self::default();
+        for intf in interfaces.iter().map(|p| &p.container) {
+            let kind = intf.kind.as_ref().map_or("unknown", |s| &s[..]);
+            *summary.kinds.entry(kind.to_string()).or_insert(0) += 1;
+            let state = intf.oper_state.as_ref().map_or("UNKNOWN", |s| &s[..]);
+            *summary.oper_states.entry(state.to_string()).or_insert(0) += 1;
+        }
+        summary
+    }
+}
+
+impl Output {
+    /// The node side of the primary interface of the container, i.e. `eth0`
+    fn primary_node_intf(&self) -> Option<&VethIntf> {
+        self.interfaces
+            .iter()
+            .find(|p| p.container.name == PRIMARY_INTF)
+            .and_then(|p| p.node.as_ref())
+    }
+
+    fn new(container: Container, interfaces: Vec<VethIntfPair>) -> Self {
+        let mut problems = vec![];
+
+        // the pod IP is what kubernetes thinks the address is, any difference from the
+        // interfaces of the container points to a CNI IPAM issue
+        if let Some(ref pod_ip) = container.pod_ip {
+            let found = interfaces.iter().any(|p| {
+                p.container
+                    .ip_address
+                    .as_ref()
+                    .and_then(|ip| ip.split('/').next())
+                    == Some(&pod_ip[..])
+            });
+            if !found {
+                problems.push(Problem::error(
+                    "pod-ip-missing",
+                    format!(
+                        "pod IP {} reported by kubernetes is not configured on any interface",
+                        pod_ip
+                    ),
+                ));
+            }
+        }
+
+        // both ends of a veth pair are expected to have the same MTU and to be up together
+        for pair in interfaces.iter() {
+            let c = &pair.container;
+            let n = match pair.node {
+                Some(ref n) => n,
+                // e.g. with --self, the links that are not Ethernet are often down on purpose
+                None => {
+                    if c.is_ether() && !c.is_up() {
+                        problems.push(Problem::warning(
+                            "link-down",
+                            format!("{} (container) is DOWN", c.name),
+                        ));
+                    }
+                    continue;
+                }
+            };
+            if c.mtu != n.mtu {
+                problems.push(Problem::warning(
+                    "mtu-mismatch",
+                    format!(
+                        "MTU mismatch on the veth pair: {} (container) has {}, {} (node) has {}",
+                        c.name, c.mtu, n.name, n.mtu
+                    ),
+                ));
+            }
+            let (c_up, n_up) = (c.is_up(), n.is_up());
+            if c_up != n_up {
+                problems.push(Problem::error(
+                    "link-state-mismatch",
+                    format!(
+                        "link state mismatch on the veth pair: {} (container) is {}, {} (node) is {}",
+                        c.name,
+                        if c_up { "UP" } else { "DOWN" },
+                        n.name,
+                        if n_up { "UP" } else { "DOWN" }
+                    ),
+                ));
+            } else if !c_up {
+                problems.push(Problem::warning(
+                    "link-down",
+                    format!(
+                        "both ends of the veth pair are DOWN: {} (container), {} (node)",
+                        c.name, n.name
+                    ),
+                ));
+            }
+        }
+
+        for pair in interfaces.iter() {
+            let sides = [
+                ("container", Some(&pair.container)),
+                ("node", pair.node.as_ref()),
+            ];
+            for (side, intf) in sides.iter() {
+                if let Some(intf) = intf.filter(|i| i.master_chain_truncated) {
+                    problems.push(Problem::warning(
+                        "master-chain-truncated",
+                        format!(
+                            "the master chain of {} ({}) is deeper than {} levels, see --max-depth",
+                            intf.name,
+                            side,
+                            intf.master_chain.len()
+                        ),
+                    ));
+                }
+            }
+        }
+
+        // an address stuck in `tentative` or marked `dadfailed` can't be used, e.g. another
+        // host on the link already has the same IPv6 address
+        for pair in interfaces.iter() {
+            for addr in pair.container.addresses.iter().filter(|a| a.is_unusable()) {
+                problems.push(Problem::error(
+                    "address-unusable",
+                    format!(
+                        "address {} on {} (container) is not usable, flags: {}",
+                        addr.address,
+                        pair.container.name,
+                        addr.flags.join(",")
+                    ),
+                ));
+            }
+        }
+
+        // hostNetwork in the pod spec should match what the kernel reports
+        if let (Some(spec), Some(observed)) = (container.spec_host_network, container.host_network)
+        {
+            if spec != observed {
+                problems.push(Problem::warning(
+                    "host-network-mismatch",
+                    format!(
+                        "pod spec has hostNetwork: {} but the container {} the host network namespace",
+                        spec,
+                        if observed { "shares" } else { "does not share" }
+                    ),
+                ));
+            }
+        }
+
+        let summary = IntfSummary::new(&interfaces);
+        Self {
+            output_version: OUTPUT_VERSION,
+            container,
+            interfaces,
+            problems,
+            sysctls: None,
+            gateways: None,
+            summary,
+            skipped_interfaces: 0,
+            ifindex_churn: None,
+        }
+    }
+}
+
+/// The error hit for a target skipped in best-effort mode, e.g. a pod that is terminating
+#[derive(Debug, Serialize)]
+struct TargetError {
+    target: String,
+    error: String,
+    caused_by: Vec<String>,
+}
+
+impl TargetError {
+    /// The entry listing the target in the results with --report-missing
+    fn missing_entry(&self) -> serde_json::Value {
+        json!({
+            "target": self.target,
+            "found": false,
+            "reason": self.error,
+        })
+    }
+
+    fn new(target: String, e: &Error) -> Self {
+        let mut fail: &Fail = e.cause();
+        let mut caused_by = vec![];
+        while let Some(cause) = fail.cause() {
+            caused_by.push(cause.to_string());
+            fail = cause;
+        }
+        Self {
+            target,
+            error: e.cause().to_string(),
+            caused_by,
+        }
+    }
+}
+
+/// A self-describing wrapper around the output, used for archiving the reports
+#[derive(Debug, Serialize)]
+struct Envelope<'a> {
+    output_version: u32,
+    timestamp: String,
+    node: Option<String>,
+    tool_version: &'static str,
+    results: &'a [Output],
+    errors: &'a [TargetError],
+}
+
+impl<'a> Envelope<'a> {
+    fn new(results: &'a [Output], errors: &'a [TargetError]) -> Self {
+        // prefer the node name reported by kubernetes and fall back to the kernel hostname
+        let node = results
+            .iter()
+            .filter_map(|o| o.container.node_name.clone())
+            .next()
+            .or_else(|| {
+                std::fs::read_to_string("/proc/sys/kernel/hostname")
+                    .ok()
+                    .map(|s| s.trim().to_string())
+            });
+        Self {
+            output_version: OUTPUT_VERSION,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            node,
+            tool_version: semver(),
+            results,
+            errors,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct VethIntf {
+    name: String,
+    #[serde(default)]
+    altnames: Vec<String>,
+    ifindex: u16,
+    // 0 for the links without a peer, e.g. an ipip tunnel or an SR-IOV VF
+    peer_ifindex: u16,
+    #[serde(default)]
+    flags: Vec<String>,
+    // the `IFF_*` bits of the flags, for the consumers checking the bits instead of the names
+    #[serde(default)]
+    flags_raw: u32,
+    // the link layer type, e.g. ether or ipip
+    #[serde(default = "default_link_type")]
+    link_type: String,
+    mtu: u16,
+    // the link kind, e.g. veth or macvlan, and the operational state, e.g. UP or DOWN
+    #[serde(default)]
+    kind: Option<String>,
+    #[serde(default)]
+    oper_state: Option<String>,
+    // whether the device detects a carrier, i.e. the raw lower layer state that can disagree
+    // with the operational one, e.g. for a dormant link; missing if administratively down
+    #[serde(default)]
+    carrier: Option<bool>,
+    // the MTU range supported by the device, only reported by newer kernels
+    min_mtu: Option<u32>,
+    max_mtu: Option<u32>,
+    txqueuelen: Option<u32>,
+    // the largest GSO/GRO packets the device accepts, only reported by `ip -d` on newer
+    // kernels, `gro_max_size` only since 5.19
+    #[serde(default)]
+    gso_max_size: Option<u32>,
+    #[serde(default)]
+    gso_max_segs: Option<u32>,
+    #[serde(default)]
+    gro_max_size: Option<u32>,
+    // the number of TX/RX queues of the device, e.g. to check RSS/RPS, only reported by
+    // `ip -d`
+    #[serde(default)]
+    num_tx_queues: Option<u32>,
+    #[serde(default)]
+    num_rx_queues: Option<u32>,
+    // the link mode, e.g. DEFAULT or DORMANT, only reported by `ip link show`
+    linkmode: Option<String>,
+    mac_address: String,
+    // whether the U/L bit of the MAC address is set, i.e. the address was generated,
+    // e.g. by the CNI plugin, instead of being burned-in; missing if not a MAC address
+    #[serde(default)]
+    locally_administered: Option<bool>,
+    // the vendor of the MAC address prefix, set with --mac-oui-lookup
+    #[serde(default)]
+    vendor: Option<String>,
+    broadcast: Option<String>,
+    bridge: Option<String>,
+    // the bridge followed by its own masters, if any, e.g. `[br-inner, br-outer, bond0]`
+    #[serde(default)]
+    master_chain: Vec<String>,
+    // set if the master chain is longer than --max-depth, only the first masters are listed
+    #[serde(default)]
+    master_chain_truncated: bool,
+    // the kind of the master the interface is enslaved to, e.g. bridge or bond, and the
+    // state of the port from the slave side, e.g. forwarding for a bridge or ACTIVE for a bond
+    #[serde(default)]
+    slave_kind: Option<String>,
+    #[serde(default)]
+    slave_state: Option<String>,
+    ip_address: Option<String>,
+    // every IPv4/IPv6 address with its scope and flags, only reported by `ip addr show`
+    #[serde(default)]
+    addresses: Vec<IpAddr>,
+    // the port name and the switch id of switchdev ports, e.g. the SR-IOV VF representors,
+    // used to map the representors to the physical switch ports
+    #[serde(default)]
+    phys_port_name: Option<String>,
+    #[serde(default)]
+    phys_switch_id: Option<String>,
+    // the link the interface is on, e.g. the parent of a vlan or macvlan, if it is in the same
+    // namespace
+    #[serde(default)]
+    link: Option<String>,
+    // the id, relative to the namespace of the interface, of the namespace holding the peer;
+    // for the node side of a pair this is the namespace of the container reported along
+    link_netnsid: Option<u32>,
+}
+
+// an address configured on an interface, e.g. `inet6 fe80::858:aff:fef4:de/64 scope link`
+// the flags are the ones printed by `ip addr show`, e.g. `tentative` or `deprecated`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct IpAddr {
+    family: String,
+    address: String,
+    scope: Option<String>,
+    #[serde(default)]
+    flags: Vec<String>,
+    // the lifetimes printed by `ip addr show` after the address, e.g. to spot a DHCPv6 or
+    // SLAAC address about to expire
+    #[serde(default)]
+    valid_lft: Option<AddrLifetime>,
+    #[serde(default)]
+    preferred_lft: Option<AddrLifetime>,
+}
+
+/// The valid or preferred lifetime of an address, `forever` for the permanent ones
+///
+/// It is serialized as the number of seconds or as the `"forever"` string
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AddrLifetime {
+    Forever,
+    Secs(u32),
+}
+
+impl serde::Serialize for AddrLifetime {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            AddrLifetime::Forever => serializer.serialize_str("forever"),
+            AddrLifetime::Secs(v) => serializer.serialize_u32(v),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for AddrLifetime {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Secs(u32),
+            Name(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Secs(v) => Ok(AddrLifetime::Secs(v)),
+            Raw::Name(ref v) if v == "forever" => Ok(AddrLifetime::Forever),
+            Raw::Name(v) => Err(serde::de::Error::custom(format!(
+                "invalid address lifetime {}",
+                v
+            ))),
+        }
+    }
+}
+
+impl AddrLifetime {
+    /// Parse a lifetime as printed by `ip addr show`, e.g. `forever` or `3599sec`
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "forever" => Some(AddrLifetime::Forever),
+            _ => s
+                .trim_end_matches("sec")
+                .parse()
+                .ok()
+                .map(AddrLifetime::Secs),
+        }
+    }
+
+    /// The lifetime in seconds as reported by `ip -j addr show`, where `forever` is the
+    /// largest 32-bit value
+    fn to_iproute2(self) -> u32 {
+        match self {
+            AddrLifetime::Forever => u32::MAX,
+            AddrLifetime::Secs(v) => v,
+        }
+    }
+}
+
+impl IpAddr {
+    /// Convert the address to an `addr_info` entry of `ip -j addr show`
+    fn to_iproute2(&self) -> serde_json::Value {
+        let mut addr = serde_json::Map::new();
+        addr.insert("family".into(), json!(self.family));
+        let mut parts = self.address.splitn(2, '/');
+        addr.insert("local".into(), json!(parts.next()));
+        if let Some(Ok(len)) = parts.next().map(|v| v.parse::<u8>()) {
+            addr.insert("prefixlen".into(), json!(len));
+        }
+        if let Some(ref scope) = self.scope {
+            addr.insert("scope".into(), json!(scope));
+        }
+        // iproute2 reports the flags as booleans, e.g. `"tentative": true`
+        for flag in self.flags.iter() {
+            addr.insert(flag.clone(), json!(true));
+        }
+        if let Some(v) = self.valid_lft {
+            addr.insert("valid_life_time".into(), json!(v.to_iproute2()));
+        }
+        if let Some(v) = self.preferred_lft {
+            addr.insert("preferred_life_time".into(), json!(v.to_iproute2()));
+        }
+        serde_json::Value::Object(addr)
+    }
+
+    /// Check if the address is not usable because duplicate address detection
+    /// is still in progress or it failed
+    fn is_unusable(&self) -> bool {
+        self.flags
+            .iter()
+            .any(|f| f == "tentative" || f == "dadfailed")
+    }
+}
+
+// a pair of container/node interfaces, e.g. a veth pair
+// the node interface is not known when cniguru inspects the container it runs in
+#[derive(Debug, Serialize, Deserialize)]
+struct VethIntfPair {
+    container: VethIntf,
+    node: Option<VethIntf>,
+    traffic: Option<TrafficSample>,
+    // set with --related for the pairs pulled in by a matching one
+    #[serde(default)]
+    related: bool,
+    // set with --annotate-from-crd for the extra interfaces of Multus pods
+    #[serde(default)]
+    network: Option<k8s::NetworkAttachment>,
+    // set with --bridge-vlans if the node interface is a port of a VLAN filtering bridge
+    #[serde(default)]
+    bridge_vlans: Option<BridgeVlans>,
+}
+
+/// The VLAN membership of a bridge port as listed by `bridge vlan show`, the ranges
+/// are kept as printed, e.g. `20-25`
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+struct BridgeVlans {
+    pvid: Option<u16>,
+    tagged: Vec<String>,
+    untagged: Vec<String>,
+}
+
+/// The link layer type of the interfaces in the reports saved before it was reported
+fn default_link_type() -> String {
+    "ether".to_string()
+}
+
+impl VethIntf {
+    /// Convert the interface to the `ip -j addr show` schema, the fields that are not
+    /// known are left out as iproute2 does
+    fn to_iproute2(&self) -> serde_json::Value {
+        let mut link = serde_json::Map::new();
+        {
+            let mut add = |key: &str, val: serde_json::Value| {
+                if !val.is_null() {
+                    link.insert(key.to_string(), val);
+                }
+            };
+            add("ifindex", json!(self.ifindex));
+            if self.peer_ifindex != 0 {
+                add("link_index", json!(self.peer_ifindex));
+            }
+            add("ifname", json!(self.name));
+            add("flags", json!(self.flags));
+            add("mtu", json!(self.mtu));
+            add("master", json!(self.bridge));
+            add("operstate", json!(self.oper_state));
+            add("linkmode", json!(self.linkmode));
+            add("txqlen", json!(self.txqueuelen));
+            add("link_type", json!(self.link_type));
+            if !self.mac_address.is_empty() {
+                add("address", json!(self.mac_address));
+            }
+            add("broadcast", json!(self.broadcast));
+            add("link", json!(self.link));
+            add("link_netnsid", json!(self.link_netnsid));
+            add("min_mtu", json!(self.min_mtu));
+            add("max_mtu", json!(self.max_mtu));
+            if self.kind.is_some() || self.slave_kind.is_some() {
+                let mut info = serde_json::Map::new();
+                if let Some(ref kind) = self.kind {
+                    info.insert("info_kind".into(), json!(kind));
+                }
+                if let Some(ref kind) = self.slave_kind {
+                    info.insert("info_slave_kind".into(), json!(kind));
+                }
+                add("linkinfo", serde_json::Value::Object(info));
+            }
+            add("gso_max_size", json!(self.gso_max_size));
+            add("gso_max_segs", json!(self.gso_max_segs));
+            add("gro_max_size", json!(self.gro_max_size));
+            add("num_tx_queues", json!(self.num_tx_queues));
+            add("num_rx_queues", json!(self.num_rx_queues));
+            add("phys_port_name", json!(self.phys_port_name));
+            add("phys_switch_id", json!(self.phys_switch_id));
+            if !self.altnames.is_empty() {
+                add("altnames", json!(self.altnames));
+            }
+            let addr_info: Vec<_> = self.addresses.iter().map(|a| a.to_iproute2()).collect();
+            add("addr_info", json!(addr_info));
+        }
+        serde_json::Value::Object(link)
     }
-    if std::env::var("RUST_BACKTRACE").is_ok() {
-        write!(f, "{}\n", e.backtrace()).expect("could not write to stderr");
+
+    /// Check if the given address is configured on the interface, regardless of the prefix
+    fn has_address(&self, ip: &std::net::IpAddr) -> bool {
+        self.addresses
+            .iter()
+            .map(|a| &a.address)
+            .chain(self.ip_address.iter())
+            .filter_map(|a| a.split('/').next())
+            .any(|a| a.parse::<std::net::IpAddr>().ok().as_ref() == Some(ip))
     }
-    std::process::exit(1);
-}
 
-/// Print the error in JSON format and exit with code `1`
-fn print_err_as_json_and_exit(e: Error) {
-    let mut fail: &Fail = e.cause();
-    let mut caused_by = vec![];
-    while let Some(cause) = fail.cause() {
-        caused_by.push(cause.to_string());
-        fail = cause;
+    /// Check if the link layer of the interface is Ethernet, e.g. a veth, macvlan or vlan
+    fn is_ether(&self) -> bool {
+        self.link_type == "ether"
     }
 
-    let err_str = json!({
-        "error": e.cause().to_string(),
-        "caused_by": caused_by
-    });
+    /// Check if the interface is administratively up
+    fn is_up(&self) -> bool {
+        self.flags.iter().any(|f| f == "UP")
+    }
 
-    println!("{}", err_str);
-    std::process::exit(1);
+    /// Check if the name or one of the alternative names of the interface matches the regex
+    fn name_matches(&self, re: &Regex) -> bool {
+        re.is_match(&self.name) || self.altnames.iter().any(|n| re.is_match(n))
+    }
 }
 
-/// Pretty print the output and exit with code `0`
-fn pretty_print_output_and_exit(output: Vec<Output>) {
-    let mut r = vec![];
-
-    if output.len() > 0 {
-        let l =
-            "CONTAINER_ID\tPID\tNODE\tINTF(C)\tMAC_ADDRESS(C)\tIP_ADDRESS(C)\tINTF(N)\tBRIDGE(N)"
-                .to_string();
-        r.push(l);
+impl VethIntfPair {
+    /// Check if the name of the container or the node interface matches the regex
+    fn name_matches(&self, re: &Regex) -> bool {
+        let node_matches = match self.node {
+            Some(ref n) => n.name_matches(re),
+            None => false,
+        };
+        self.container.name_matches(re) || node_matches
     }
 
-    for i in output {
-        let short_id = &i.container.id[0..12];
-        for intf in i.interfaces {
-            let l = format!(
-                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
-                short_id,
-                i.container.pid,
-                i.container.node_name.as_ref().map_or("-", |s| &s[..]),
-                &intf.container.name,
-                &intf.container.mac_address,
-                &intf.container.ip_address.as_ref().map_or("-", |s| &s[..]),
-                &intf.node.name,
-                intf.node.bridge.as_ref().map_or("-", |s| &s[..])
-            );
-            r.push(l);
-        }
+    /// The bridge the node interface is plugged into, if any
+    fn bridge(&self) -> Option<&str> {
+        self.node
+            .as_ref()
+            .and_then(|n| n.bridge.as_ref())
+            .map(|s| &s[..])
     }
+}
 
-    let output_string = r.join("\n");
-    let tw = TabWriter::new(Vec::<u8>::new());
-    println!(
-        "\n{}\n",
-        tabify(tw, &output_string[..]).expect("failed to format the output")
-    );
-    std::process::exit(0);
+/// The traffic seen by a container interface during a capture, i.e. the deltas of its
+/// `/proc/net/dev` counters
+#[derive(Debug, Serialize, Deserialize)]
+struct TrafficSample {
+    secs: u64,
+    rx_packets: u64,
+    rx_bytes: u64,
+    tx_packets: u64,
+    tx_bytes: u64,
 }
 
-/// Align the tab separated values to make them look nice
-pub fn tabify(mut tw: TabWriter<Vec<u8>>, s: &str) -> Result<String, Error> {
-    write!(&mut tw, "{}", s)?;
-    tw.flush()?;
-    Ok(String::from_utf8(tw.into_inner()?)?)
+// the value of a sysctl in the container network namespace,
+// missing if the key does not exist there, e.g. IPv6 is disabled
+#[derive(Debug, Serialize, Deserialize)]
+struct Sysctl {
+    key: String,
+    value: Option<String>,
 }
 
-/// The output data structure
-#[derive(Debug, Serialize)]
-struct Output {
-    container: Container,
-    interfaces: Vec<VethIntfPair>,
+// a default gateway of the container and the state of its neighbor entry, e.g. REACHABLE,
+// missing if there is no entry for the gateway at all
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Gateway {
+    address: String,
+    dev: Option<String>,
+    state: Option<String>,
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
-struct VethIntf {
-    name: String,
-    ifindex: u16,
-    peer_ifindex: u16,
-    mtu: u16,
-    mac_address: String,
-    bridge: Option<String>,
-    ip_address: Option<String>,
+impl Gateway {
+    /// Check if the link layer address of the gateway is known, i.e. the container is
+    /// likely able to send packets to it
+    fn is_resolved(&self) -> bool {
+        match self.state {
+            Some(ref s) => !["FAILED", "INCOMPLETE", "NONE"].contains(&&s[..]),
+            None => false,
+        }
+    }
 }
 
-// a pair of container/node interfaces, e.g. a veth pair
-#[derive(Debug, Serialize)]
-struct VethIntfPair {
-    container: VethIntf,
-    node: VethIntf,
+/// The counters of an interface as reported by `/proc/net/dev`
+#[derive(Debug, PartialEq, Eq)]
+struct NetDevStats {
+    rx_bytes: u64,
+    rx_packets: u64,
+    tx_bytes: u64,
+    tx_packets: u64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum ContainerRuntime {
     Docker,
+    Podman,
 }
 
-#[derive(Debug, Serialize)]
+/// The runtime of the containers given with `dc`, podman if the API socket is set
+fn dc_runtime(cfg: &Config) -> ContainerRuntime {
+    match cfg.podman_socket {
+        Some(_) => ContainerRuntime::Podman,
+        None => ContainerRuntime::Docker,
+    }
+}
+
+/// Fetch the `inspect` json of a podman container, the socket must be set
+fn inspect_podman_container(cfg: &Config, id: &str) -> Result<serde_json::Value, Error> {
+    let socket = match cfg.podman_socket {
+        Some(ref v) => v,
+        None => Err(error::InvalidArgsErr("--podman-socket is not set"))?,
+    };
+    podman::inspect_container(socket, id)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Container {
     pub id: String,
+    pub container_name: Option<String>,
     pub pid: u32,
     pub node_name: Option<String>,
+    pub pod_name: Option<String>,
+    pub namespace: Option<String>,
+    pub pod_ip: Option<String>,
+    // whether the container shares the namespaces with the host, e.g. `hostPID`
+    pub host_network: Option<bool>,
+    pub host_pid: Option<bool>,
+    pub host_ipc: Option<bool>,
+    // the network namespace in the `net:[<inode>]` form used by `lsns` and `ss -p`
+    #[serde(default)]
+    pub netns: Option<String>,
+    // set with --explain-host-network
+    pub host_network_basis: Option<String>,
+    // the `hostNetwork` from the pod spec, used to cross-check `host_network`
+    #[serde(skip, default)]
+    pub spec_host_network: Option<bool>,
+    // the Multus networks annotation of the pod, used by --annotate-from-crd
+    #[serde(skip, default)]
+    pub networks_annotation: Option<String>,
+    // set for the kubernetes ephemeral (debug) containers, included with --ephemeral
+    #[serde(default)]
+    pub ephemeral: bool,
+    // the QoS class of the pod and the NetworkPolicies selecting it, set with --with-netpol
+    #[serde(default)]
+    pub qos_class: Option<String>,
+    #[serde(default)]
+    pub network_policies: Option<Vec<String>>,
     pub runtime: ContainerRuntime,
 }
 
 impl Container {
-    fn new(id: String, runtime: ContainerRuntime) -> Result<Self, Error> {
+    fn new(cfg: &Config, id: String, runtime: ContainerRuntime) -> Result<Self, Error> {
         // Retrieve the `pid` of the container
-        let pid = match runtime {
+        let (id, pid) = match runtime {
             ContainerRuntime::Docker => {
                 // fetch the PID using docker CLI
                 // a docker client is not currently used as it's hard to find a lightweight
@@ -261,7 +2950,20 @@ impl Container {
                 let cmd = format!("docker inspect {} --format '{{{{.State.Pid}}}}'", &id);
                 let output = run_host_cmd(&cmd)?;
                 let pid: u32 = output.trim_matches('\'').parse()?;
-                pid
+                (id, pid)
+            }
+            ContainerRuntime::Podman => {
+                debug!("trying to find the pid for podman container {}", &id);
+                let v = inspect_podman_container(cfg, &id)?;
+                // the container can be given by name, the full id is reported instead
+                let id = match v["Id"].as_str() {
+                    Some(v) => v.to_string(),
+                    None => Err(error::PodmanError::MissingField("Id"))?,
+                };
+                match v["State"]["Pid"].as_u64() {
+                    Some(pid) => (id, pid as u32),
+                    None => Err(error::PodmanError::MissingField("State.Pid"))?,
+                }
             }
         };
 
@@ -269,94 +2971,855 @@ impl Container {
             id,
             pid,
             runtime,
+            container_name: None,
+            node_name: None,
+            pod_name: None,
+            namespace: None,
+            pod_ip: None,
+            host_network: None,
+            host_pid: None,
+            host_ipc: None,
+            netns: None,
+            host_network_basis: None,
+            spec_host_network: None,
+            networks_annotation: None,
+            ephemeral: false,
+            qos_class: None,
+            network_policies: None,
+        };
+        debug!("new Container: {:?}", &container);
+        Ok(container)
+    }
+
+    /// Create the `Container` cniguru itself is running in
+    ///
+    /// The container id is extracted from the cgroup the process belongs to or,
+    /// if cgroup namespaces hide it, from the docker mounts of the container
+    fn new_self(cfg: &Config) -> Result<Self, Error> {
+        lazy_static! {
+            static ref CGROUP_RE: Regex =
+                Regex::new(r"(?P<rt>docker|crio|cri-containerd)[-/](?P<id>[0-9a-f]{64})").unwrap();
+            static ref MOUNTINFO_RE: Regex =
+                Regex::new(r"/(?P<rt>docker)/containers/(?P<id>[0-9a-f]{64})/").unwrap();
+        }
+
+        let mut found = None;
+        for &(file, re) in &[
+            ("self/cgroup", &*CGROUP_RE),
+            ("self/mountinfo", &*MOUNTINFO_RE),
+        ] {
+            let file = format!("{}/{}", cfg.proc_path, file);
+            debug!("looking for the id of the current container in {}", file);
+            let content = std::fs::read_to_string(&file)?;
+            if let Some(m) = re.captures(&content) {
+                found = Some((m["rt"].to_string(), m["id"].to_string()));
+                break;
+            }
+        }
+
+        let (runtime, id) = match found {
+            Some((ref rt, ref id)) if rt == "docker" => (ContainerRuntime::Docker, id.to_string()),
+            Some((rt, _)) => Err(error::SelfContainerError::UnsupportedRuntime(rt))?,
+            None => Err(error::SelfContainerError::IdMissing)?,
+        };
+
+        let container = Self {
+            id,
+            pid: std::process::id(),
+            runtime,
+            container_name: None,
             node_name: None,
+            pod_name: None,
+            namespace: None,
+            pod_ip: None,
+            host_network: None,
+            host_pid: None,
+            host_ipc: None,
+            netns: None,
+            host_network_basis: None,
+            spec_host_network: None,
+            networks_annotation: None,
+            ephemeral: false,
+            qos_class: None,
+            network_policies: None,
         };
         debug!("new Container: {:?}", &container);
         Ok(container)
     }
 
+    /// Fill in the kubernetes pod name, namespace and container name using the labels set
+    /// on the container by the kubelet, if any
+    fn load_pod_info(&mut self, cfg: &Config) -> Result<(), Error> {
+        let labels = match self.runtime {
+            ContainerRuntime::Docker => {
+                debug!("fetching the labels of docker container {}", &self.id);
+                let cmd = format!("docker inspect {}", &self.id);
+                let output = run_host_cmd(&cmd)?;
+                let v: serde_json::Value = serde_json::from_str(&output)?;
+                v[0]["Config"]["Labels"].clone()
+            }
+            ContainerRuntime::Podman => {
+                debug!("fetching the labels of podman container {}", &self.id);
+                inspect_podman_container(cfg, &self.id)?["Config"]["Labels"].clone()
+            }
+        };
+        self.pod_name = labels["io.kubernetes.pod.name"]
+            .as_str()
+            .map(|s| s.to_string());
+        self.namespace = labels["io.kubernetes.pod.namespace"]
+            .as_str()
+            .map(|s| s.to_string());
+        self.container_name = labels["io.kubernetes.container.name"]
+            .as_str()
+            .map(|s| s.to_string());
+        Ok(())
+    }
+
+    /// Check if the container shares the given namespace type, e.g. `pid`, with the host
+    ///
+    /// The namespaces are the same if the `/proc/<pid>/ns/<kind>` links of the container
+    /// process and of the host init process point to the same inode, e.g. `pid:[4026531836]`
+    fn shares_host_ns(&self, cfg: &Config, kind: &str) -> Option<bool> {
+        Some(read_ns_link(cfg, self.pid, kind)? == read_ns_link(cfg, 1, kind)?)
+    }
+
+    /// Explain why the container is considered to use, or not, the host network namespace
+    fn explain_host_network(&self, cfg: &Config) -> Option<String> {
+        let cns = read_ns_link(cfg, self.pid, "net")?;
+        let hns = read_ns_link(cfg, 1, "net")?;
+        let mut basis = if cns == hns {
+            format!(
+                "container netns inode {} equals pid 1 netns inode",
+                cns.display()
+            )
+        } else {
+            format!(
+                "container netns inode {} differs from pid 1 netns inode {}",
+                cns.display(),
+                hns.display()
+            )
+        };
+        if let Some(v) = self.spec_host_network {
+            basis.push_str(&format!(", pod spec has hostNetwork: {}", v));
+        }
+        Some(basis)
+    }
+
     /// Get the list of container interfaces
-    fn get_container_interfaces(&self) -> Result<Vec<VethIntf>, Error> {
+    fn get_container_interfaces(&self, cfg: &Config) -> Result<Vec<VethIntf>, Error> {
         debug!(
-            "fetching `ip addr show` printout for container {}",
+            "fetching `ip -d addr show` printout for container {}",
             &self.id
         );
-        let cmd = format!("nsenter -t {} -n -- ip addr show", &self.pid);
+        // no need to switch the network namespace if cniguru runs in the container
+        let cmd = if self.pid == std::process::id() {
+            "ip -d addr show".to_string()
+        } else {
+            nsenter_net_cmd(cfg, self.pid, "ip -d addr show")
+        };
+        let output = run_host_cmd(&cmd)?;
+
+        let mut intfs = parse_ip_link_or_addr_printout(&output, cfg.max_master_chain_len)?;
+        // the addresses of all the families are listed by the same command
+        if cfg.no_ipv6 {
+            drop_ipv6_addresses(&mut intfs);
+        }
+        Ok(intfs)
+    }
+
+    /// Get the interface counters from the container network namespace
+    fn get_netdev_stats(&self, cfg: &Config) -> Result<HashMap<String, NetDevStats>, Error> {
+        debug!("fetching /proc/net/dev for container {}", &self.id);
+        // `/proc/net` shows the network namespace of the process reading it
+        let cmd = if self.pid == std::process::id() {
+            "cat /proc/net/dev".to_string()
+        } else {
+            nsenter_net_cmd(cfg, self.pid, "cat /proc/net/dev")
+        };
         let output = run_host_cmd(&cmd)?;
 
-        parse_ip_link_or_addr_printout(&output)
+        parse_proc_net_dev(&output)
+    }
+
+    /// Find the IPv4 and IPv6 default gateways in the container network namespace and
+    /// check if they have a neighbor entry, only the IPv4 ones with --no-ipv6
+    fn get_gateways(&self, cfg: &Config) -> Result<Vec<Gateway>, Error> {
+        let mut res = vec![];
+        let families: &[&str] = if cfg.no_ipv6 { &["-4"] } else { &["-4", "-6"] };
+        for family in families {
+            debug!(
+                "fetching the {} default routes for container {}",
+                family, &self.id
+            );
+            let routes_cmd = format!("ip {} route show default", family);
+            let cmd = if self.pid == std::process::id() {
+                routes_cmd
+            } else {
+                nsenter_net_cmd(cfg, self.pid, &routes_cmd)
+            };
+            for (address, dev) in parse_default_routes(&run_host_cmd(&cmd)?) {
+                let mut neigh_cmd = format!("ip {} neigh show {}", family, address);
+                if let Some(ref dev) = dev {
+                    neigh_cmd.push_str(&format!(" dev {}", dev));
+                }
+                let cmd = if self.pid == std::process::id() {
+                    neigh_cmd
+                } else {
+                    nsenter_net_cmd(cfg, self.pid, &neigh_cmd)
+                };
+                let state = parse_neigh_state(&run_host_cmd(&cmd)?);
+                res.push(Gateway {
+                    address,
+                    dev,
+                    state,
+                });
+            }
+        }
+        Ok(res)
+    }
+
+    /// Read the given sysctls in the container network namespace
+    fn get_sysctls(&self, cfg: &Config, keys: &[&str]) -> Result<Vec<Sysctl>, Error> {
+        let mut res = vec![];
+        for key in keys {
+            let path = sysctl_key_to_path(key)?;
+            debug!("fetching sysctl {} for container {}", key, &self.id);
+            // `/proc/sys/net` shows the network namespace of the process reading it
+            let cmd = if self.pid == std::process::id() {
+                format!("cat {}", path)
+            } else {
+                nsenter_net_cmd(cfg, self.pid, &format!("cat {}", path))
+            };
+            let value = match run_host_cmd(&cmd) {
+                Ok(v) => Some(v),
+                Err(e) => match e.downcast::<error::HostCmdError>() {
+                    Ok(e) => {
+                        debug!("failed to read sysctl {}: {}", key, e);
+                        None
+                    }
+                    Err(e) => return Err(e),
+                },
+            };
+            res.push(Sysctl {
+                key: key.to_string(),
+                value,
+            });
+        }
+        Ok(res)
     }
 
     /// create a list of interface pairs,
     /// i.e. the container interfaces and their corresponding node interface
-    fn interfaces(&self) -> Result<Vec<VethIntfPair>, Error> {
-        // fetch the node interfaces
-        debug!("fetching node `ip link show` printout");
-        let cmd = "ip link show";
-        let output = run_host_cmd(cmd)?;
+    fn interfaces(&self, cfg: &Config) -> Result<Vec<VethIntfPair>, Error> {
+        let node_intfs = get_node_interfaces(cfg)?;
 
-        let mut node_intfs = parse_ip_link_or_addr_printout(&output)?;
+        let container_intfs = self.get_container_interfaces(cfg)?;
 
-        let container_intfs = self.get_container_interfaces()?;
+        Ok(pair_interfaces(container_intfs, node_intfs))
+    }
+}
 
-        let mut out = vec![];
+/// Group every container interface with the node interface it is paired with
+///
+/// The pairs are matched using the peer ifindex reported by the kernel, i.e. the `@if<N>`
+/// suffix, never the names which are not related for every CNI plugin. The ifindex is only
+/// unique within a namespace so both ends must point to each other and the node ends must
+/// all have their peer in the same namespace, i.e. have the same `link-netnsid`
+///
+/// A container interface whose peer is not on the node, e.g. in another container, is kept
+/// without a node interface, its `peer_ifindex` is still reported
+fn pair_interfaces(
+    container_intfs: Vec<VethIntf>,
+    mut node_intfs: Vec<VethIntf>,
+) -> Vec<VethIntfPair> {
+    let mut out = vec![];
 
-        // group the container interface and the corresponding node interface
+    // the id of the container namespace as seen from the node, set by the first pair found
+    let mut netnsid = None;
 
-        // Rust does not allow to take out elements of a vec while iterating through it
-        // so find the index of the node interface for every container interface
-        // and use the index to extract the needed element
-        for cintf in container_intfs {
-            let err = error::IntfMissingErr(cintf.peer_ifindex);
-            let pos = node_intfs
-                .iter()
-                .position(|nintf| cintf.peer_ifindex == nintf.ifindex)
-                .ok_or(err)?;
-            let nintf = node_intfs.swap_remove(pos);
-            out.push(VethIntfPair {
-                container: cintf,
-                node: nintf,
-            });
+    // Rust does not allow to take out elements of a vec while iterating through it
+    // so find the index of the node interface for every container interface
+    // and use the index to extract the needed element
+    for cintf in container_intfs {
+        // the links that are not Ethernet, e.g. tunnels, and the physical links, e.g. the
+        // SR-IOV VFs, have no peer on the node
+        let pos = if !cintf.is_ether() || cintf.peer_ifindex == 0 {
+            None
+        } else {
+            node_intfs.iter().position(|nintf| {
+                cintf.peer_ifindex == nintf.ifindex
+                    && nintf.peer_ifindex == cintf.ifindex
+                    && (cintf.link_netnsid.is_none() || nintf.link_netnsid.is_some())
+                    && (netnsid.is_none() || nintf.link_netnsid == netnsid)
+            })
+        };
+        let nintf = pos.map(|pos| node_intfs.swap_remove(pos));
+        match nintf {
+            Some(ref n) => netnsid = netnsid.or(n.link_netnsid),
+            None if cintf.peer_ifindex != 0 => debug!(
+                "the peer {} of interface {} is not in the node namespace",
+                cintf.peer_ifindex, cintf.name
+            ),
+            None => (),
         }
-        Ok(out)
+        out.push(VethIntfPair {
+            container: cintf,
+            node: nintf,
+            traffic: None,
+            related: false,
+            network: None,
+            bridge_vlans: None,
+        });
     }
+    out
+}
+
+/// Build the command running `cmd` in the network namespace of the given process
+///
+/// The namespace is given as a path so the host `/proc` can be mounted elsewhere
+fn nsenter_net_cmd(cfg: &Config, pid: u32, cmd: &str) -> String {
+    format!("nsenter --net={}/{}/ns/net -- {}", cfg.proc_path, pid, cmd)
+}
+
+/// Replace the current process with `cmd` run in the network namespace of the container,
+/// and optionally in its mount namespace too
+///
+/// Only returns if the command could not be executed
+fn exec_in_container(cfg: &Config, container: &Container, cmd: &[String], mount: bool) -> Error {
+    use std::os::unix::process::CommandExt;
+
+    let mut nsenter = Command::new("nsenter");
+    nsenter.arg(format!("--net={}/{}/ns/net", cfg.proc_path, container.pid));
+    if mount {
+        nsenter.arg(format!(
+            "--mount={}/{}/ns/mnt",
+            cfg.proc_path, container.pid
+        ));
+    }
+    nsenter.arg("--").args(cmd);
+    debug!("running {:?} for container {}", nsenter, container.id);
+    let e = nsenter.exec();
+    Error::from(e).context("failed to run nsenter").into()
+}
+
+/// Read the `/proc/<pid>/ns/<kind>` link identifying a namespace of the process
+fn read_ns_link(cfg: &Config, pid: u32, kind: &str) -> Option<std::path::PathBuf> {
+    let path = format!("{}/{}/ns/{}", cfg.proc_path, pid, kind);
+    match std::fs::read_link(&path) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            debug!("failed to read {}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Get the id of the network namespace of the process, e.g. `net:[4026532562]`
+fn netns_id(cfg: &Config, pid: u32) -> Option<String> {
+    read_ns_link(cfg, pid, "net").map(|v| v.to_string_lossy().into_owned())
+}
+
+/// Remove the IPv6 addresses of the interfaces, used with --no-ipv6
+fn drop_ipv6_addresses(intfs: &mut [VethIntf]) {
+    for intf in intfs.iter_mut() {
+        intf.addresses.retain(|a| a.family != "inet6");
+    }
+}
+
+/// Get the list of node interfaces
+fn get_node_interfaces(cfg: &Config) -> Result<Vec<VethIntf>, Error> {
+    debug!("fetching node `ip -d link show` printout");
+    let cmd = "ip -d link show";
+    let output = run_host_cmd(cmd)?;
+
+    parse_ip_link_or_addr_printout(&output, cfg.max_master_chain_len)
+}
+
+lazy_static! {
+    // the first line of an interface block, e.g. `3: eth0@if558: <BROADCAST,UP> mtu 1460 ..`
+    // the `@` suffix is either the ifindex of the peer or the name of the parent link
+    static ref HEADER_RE: Regex = Regex::new(
+        r"^(?P<index>\d+):\s+(?P<name>[^\s:@]+)(?:@(?:if(?P<pindex>\d+)|(?P<link>[^\s:]+)))?:\s+<(?P<flags>[^>]*)>"
+    )
+    .unwrap();
+    static ref MASTER_RE: Regex = Regex::new(r"\smaster\s+(?P<v>\S+)").unwrap();
 }
 
+/// The number of masters followed when resolving a master chain if --max-depth is not used
+const DEFAULT_MAX_MASTER_CHAIN_LEN: usize = 8;
+
 /// Parse the output of `ip link show` or `ip addr show` and extract the interfaces
-fn parse_ip_link_or_addr_printout(printout: &str) -> Result<Vec<VethIntf>, Error> {
+///
+/// At most `max_chain_len` masters are followed when resolving the master chains
+fn parse_ip_link_or_addr_printout(
+    printout: &str,
+    max_chain_len: usize,
+) -> Result<Vec<VethIntf>, Error> {
     debug!("parsing ip link/addr printout");
     let mut res = vec![];
 
     lazy_static! {
-        static ref S: &'static str = concat!(
-            r"(?P<index>\d+):\s+(?P<name>\w+)@if(?P<pindex>\d+):",
-            r".*\s+mtu\s+(?P<mtu>\d+)\s+",
-            r"(?:.*\s+master\s+(?P<br>\S+)\s+)?",
-            r".*\s+link/ether\s+(?P<mac>(\S)+)\s+",
-            r"(.*\s+inet\s+(?P<ipv4>\S+)\s+)?",
-        );
-        static ref RE: Regex = Regex::new(&S).unwrap();
+        // every interface block starts with a non-indented `<ifindex>: ` line
+        static ref BLOCK_START_RE: Regex = Regex::new(r"(?m)^\d+:\s").unwrap();
     }
-    let err = error::IpLinkOrAddrShowParseErr;
-    for m in RE.captures_iter(printout) {
-        let intf = VethIntf {
-            name: m.name("name").ok_or(err)?.as_str().to_string(),
-            ifindex: m.name("index").ok_or(err)?.as_str().parse()?,
-            peer_ifindex: m.name("pindex").ok_or(err)?.as_str().parse()?,
-            mtu: m.name("mtu").ok_or(err)?.as_str().parse()?,
-            bridge: m.name("br").map(|v| v.as_str().to_string()),
-            mac_address: m.name("mac").ok_or(err)?.as_str().to_string(),
-            ip_address: m.name("ipv4").map(|m| m.as_str().to_string()),
-        };
-        res.push(intf);
+    let starts: Vec<usize> = BLOCK_START_RE
+        .find_iter(printout)
+        .map(|m| m.start())
+        .collect();
+    // the parent of every interface, i.e. its master or, e.g. for a vlan, the link it is on
+    let mut parents = HashMap::new();
+    for (i, start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).cloned().unwrap_or(printout.len());
+        let block = &printout[*start..end];
+        if let Some(m) = HEADER_RE.captures(block) {
+            let parent = capture(&MASTER_RE, block.lines().next().unwrap_or(""))
+                .or_else(|| m.name("link").map(|v| v.as_str()))
+                .filter(|v| *v != "NONE");
+            if let Some(parent) = parent {
+                parents.insert(m["name"].to_string(), parent.to_string());
+            }
+        }
+        if let Some(intf) = parse_ip_intf_block(block)? {
+            res.push(intf);
+        }
+    }
+
+    for intf in res.iter_mut() {
+        resolve_master_chain(intf, &parents, max_chain_len);
     }
+
     if res.len() == 0 {
-        Err(err)?
+        Err(error::IpLinkOrAddrShowParseErr)?
     } else {
         Ok(res)
     }
 }
 
+/// Walk up the masters of the interface, e.g. a bridge plugged into another bridge or into
+/// a bond, following at most `max_len` of them
+///
+/// The walk stops at the first master seen twice in case of a misconfigured loop
+fn resolve_master_chain(intf: &mut VethIntf, parents: &HashMap<String, String>, max_len: usize) {
+    let mut cur = intf.bridge.clone();
+    while let Some(name) = cur {
+        if intf.master_chain.contains(&name) {
+            break;
+        }
+        if intf.master_chain.len() >= max_len {
+            intf.master_chain_truncated = true;
+            break;
+        }
+        cur = parents.get(&name).cloned();
+        intf.master_chain.push(name);
+    }
+}
+
+/// Parse the `ip link/addr show` lines describing a single interface.
+/// Return `None` for the loopback and the interfaces without a link layer address
+///
+/// The Ethernet links without a peer, e.g. the SR-IOV VFs moved into a container, are kept
+/// with a 0 `peer_ifindex`
+fn parse_ip_intf_block(block: &str) -> Result<Option<VethIntf>, Error> {
+    lazy_static! {
+        static ref MTU_RE: Regex = Regex::new(r"\smtu\s+(?P<v>\d+)").unwrap();
+        static ref LINKMODE_RE: Regex = Regex::new(r"\smode\s+(?P<v>\S+)").unwrap();
+        static ref QLEN_RE: Regex = Regex::new(r"\sqlen\s+(?P<v>\d+)").unwrap();
+        static ref MAC_RE: Regex = Regex::new(r"\slink/ether\s+(?P<v>\S+)").unwrap();
+        static ref LINK_RE: Regex =
+            Regex::new(r"\slink/(?P<type>\S+)(?:[ \t]+(?P<addr>[0-9a-f:.]+)(?:\s|$))?").unwrap();
+        static ref BRD_RE: Regex = Regex::new(r"\slink/ether\s+\S+\s+brd\s+(?P<v>\S+)").unwrap();
+        static ref IPV4_RE: Regex = Regex::new(r"\sinet\s+(?P<v>\S+)").unwrap();
+        static ref MINMTU_RE: Regex = Regex::new(r"\sminmtu\s+(?P<v>\d+)").unwrap();
+        static ref MAXMTU_RE: Regex = Regex::new(r"\smaxmtu\s+(?P<v>\d+)").unwrap();
+        static ref GSOSIZE_RE: Regex = Regex::new(r"\sgso_max_size\s+(?P<v>\d+)").unwrap();
+        static ref GSOSEGS_RE: Regex = Regex::new(r"\sgso_max_segs\s+(?P<v>\d+)").unwrap();
+        static ref GROSIZE_RE: Regex = Regex::new(r"\sgro_max_size\s+(?P<v>\d+)").unwrap();
+        static ref NUMTXQ_RE: Regex = Regex::new(r"\snumtxqueues\s+(?P<v>\d+)").unwrap();
+        static ref NUMRXQ_RE: Regex = Regex::new(r"\snumrxqueues\s+(?P<v>\d+)").unwrap();
+        static ref ALTNAME_RE: Regex = Regex::new(r"\saltname\s+(?P<v>\S+)").unwrap();
+        static ref NETNSID_RE: Regex = Regex::new(r"\slink-netnsid\s+(?P<v>\d+)").unwrap();
+        static ref STATE_RE: Regex = Regex::new(r"\sstate\s+(?P<v>\S+)").unwrap();
+        // the kind is the first word of the line following the `link/ether` one
+        static ref KIND_RE: Regex = Regex::new(
+            r"(?m)^\s+(?P<v>veth|vxlan|macvlan|macvtap|ipvlan|ipvtap|bridge|bond|dummy|tun|vlan|geneve|ipip|gre|gretap|vrf|wireguard)(?:\s|$)"
+        ).unwrap();
+        static ref PORTNAME_RE: Regex = Regex::new(r"\sportname\s+(?P<v>\S+)").unwrap();
+        static ref SWITCHID_RE: Regex = Regex::new(r"\sswitchid\s+(?P<v>\S+)").unwrap();
+        // the lifetimes are on the line following the address, if any
+        static ref ADDR_RE: Regex = Regex::new(
+            r"(?m)^\s+(?P<family>inet6?)\s+(?P<addr>\S+)(?P<rest>.*)$(?:\n\s+valid_lft\s+(?P<valid>\S+)\s+preferred_lft\s+(?P<preferred>\S+))?"
+        )
+        .unwrap();
+        static ref SCOPE_RE: Regex = Regex::new(r"\sscope\s+(?P<v>\S+)").unwrap();
+        // e.g. `bridge_slave state forwarding priority 32 cost 2 ...`
+        static ref SLAVE_RE: Regex =
+            Regex::new(r"(?m)^[ \t]+(?P<kind>\w+)_slave(?P<rest>[ \t].*)?$").unwrap();
+    }
+    let err = error::IpLinkOrAddrShowParseErr;
+
+    // the first line holds the interface attributes, the rest the link and the addresses
+    let (header, details) = match block.find('\n') {
+        Some(pos) => block.split_at(pos),
+        None => (block, ""),
+    };
+
+    let m = HEADER_RE.captures(header).ok_or(err)?;
+    let link = match LINK_RE.captures(details) {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let link_type = link["type"].to_string();
+    let (peer_ifindex, mac_address) = if link_type == "ether" {
+        let peer_ifindex = match m.name("pindex") {
+            Some(v) => v.as_str().parse()?,
+            None => 0,
+        };
+        match capture(&MAC_RE, details) {
+            Some(v) => (peer_ifindex, v.to_string()),
+            None => return Ok(None),
+        }
+    } else if link_type == "loopback" {
+        return Ok(None);
+    } else {
+        // e.g. `link/ipip 0.0.0.0 brd 0.0.0.0` or `link/none`
+        let addr = link.name("addr").map_or("", |v| v.as_str());
+        (0, addr.to_string())
+    };
+
+    let flags: Vec<String> = m
+        .name("flags")
+        .ok_or(err)?
+        .as_str()
+        .split(',')
+        .filter(|f| !f.is_empty())
+        .map(|f| f.to_string())
+        .collect();
+
+    let slave = SLAVE_RE.captures(details);
+
+    let intf = VethIntf {
+        name: m.name("name").ok_or(err)?.as_str().to_string(),
+        altnames: ALTNAME_RE
+            .captures_iter(details)
+            .map(|c| c["v"].to_string())
+            .collect(),
+        ifindex: m.name("index").ok_or(err)?.as_str().parse()?,
+        peer_ifindex,
+        link_type,
+        carrier: carrier(&flags),
+        flags_raw: flags_to_raw(&flags),
+        flags,
+        mtu: capture(&MTU_RE, header).ok_or(err)?.parse()?,
+        kind: capture(&KIND_RE, details).map(|v| v.to_string()),
+        oper_state: capture(&STATE_RE, header).map(|v| v.to_string()),
+        min_mtu: match capture(&MINMTU_RE, details) {
+            Some(v) => Some(v.parse()?),
+            None => None,
+        },
+        max_mtu: match capture(&MAXMTU_RE, details) {
+            Some(v) => Some(v.parse()?),
+            None => None,
+        },
+        txqueuelen: match capture(&QLEN_RE, header) {
+            Some(v) => Some(v.parse()?),
+            None => None,
+        },
+        gso_max_size: match capture(&GSOSIZE_RE, details) {
+            Some(v) => Some(v.parse()?),
+            None => None,
+        },
+        gso_max_segs: match capture(&GSOSEGS_RE, details) {
+            Some(v) => Some(v.parse()?),
+            None => None,
+        },
+        gro_max_size: match capture(&GROSIZE_RE, details) {
+            Some(v) => Some(v.parse()?),
+            None => None,
+        },
+        num_tx_queues: match capture(&NUMTXQ_RE, details) {
+            Some(v) => Some(v.parse()?),
+            None => None,
+        },
+        num_rx_queues: match capture(&NUMRXQ_RE, details) {
+            Some(v) => Some(v.parse()?),
+            None => None,
+        },
+        linkmode: capture(&LINKMODE_RE, header).map(|v| v.to_string()),
+        bridge: capture(&MASTER_RE, header).map(|v| v.to_string()),
+        master_chain: vec![],
+        master_chain_truncated: false,
+        slave_kind: slave.as_ref().map(|c| c["kind"].to_string()),
+        slave_state: slave
+            .as_ref()
+            .and_then(|c| c.name("rest"))
+            .and_then(|v| capture(&STATE_RE, v.as_str()))
+            .map(|v| v.to_string()),
+        locally_administered: is_locally_administered(&mac_address),
+        mac_address,
+        vendor: None,
+        broadcast: capture(&BRD_RE, details).map(|v| v.to_string()),
+        ip_address: capture(&IPV4_RE, details).map(|v| v.to_string()),
+        addresses: ADDR_RE
+            .captures_iter(details)
+            .map(|c| IpAddr {
+                family: c["family"].to_string(),
+                address: c["addr"].to_string(),
+                scope: capture(&SCOPE_RE, &c["rest"]).map(|v| v.to_string()),
+                flags: c["rest"]
+                    .split_whitespace()
+                    .filter(|f| IP_ADDR_FLAGS.contains(f))
+                    .map(|f| f.to_string())
+                    .collect(),
+                valid_lft: c
+                    .name("valid")
+                    .and_then(|v| AddrLifetime::parse(v.as_str())),
+                preferred_lft: c
+                    .name("preferred")
+                    .and_then(|v| AddrLifetime::parse(v.as_str())),
+            })
+            .collect(),
+        phys_port_name: capture(&PORTNAME_RE, details).map(|v| v.to_string()),
+        phys_switch_id: capture(&SWITCHID_RE, details).map(|v| v.to_string()),
+        link: m
+            .name("link")
+            .map(|v| v.as_str())
+            .filter(|v| *v != "NONE")
+            .map(|v| v.to_string()),
+        link_netnsid: match capture(&NETNSID_RE, details) {
+            Some(v) => Some(v.parse()?),
+            None => None,
+        },
+    };
+    Ok(Some(intf))
+}
+
+/// The address flags printed by `ip addr show` after the scope, the other words there are
+/// the label of the address or the attributes of the peer, e.g. `brd`
+const IP_ADDR_FLAGS: &[&str] = &[
+    "secondary",
+    "temporary",
+    "deprecated",
+    "tentative",
+    "dadfailed",
+    "optimistic",
+    "nodad",
+    "noprefixroute",
+    "mngtmpaddr",
+    "dynamic",
+    "home",
+    "autojoin",
+    "stable-privacy",
+];
+
+/// The `IFF_*` bits of the link flags printed by `ip link show`, see `netdevice(7)`
+const IFF_FLAGS: &[(&str, u32)] = &[
+    ("UP", 0x1),
+    ("BROADCAST", 0x2),
+    ("DEBUG", 0x4),
+    ("LOOPBACK", 0x8),
+    ("POINTOPOINT", 0x10),
+    ("NOTRAILERS", 0x20),
+    ("NOARP", 0x80),
+    ("PROMISC", 0x100),
+    ("ALLMULTI", 0x200),
+    ("MASTER", 0x400),
+    ("SLAVE", 0x800),
+    ("MULTICAST", 0x1000),
+    ("PORTSEL", 0x2000),
+    ("AUTOMEDIA", 0x4000),
+    ("DYNAMIC", 0x8000),
+    ("LOWER_UP", 0x10000),
+    ("DORMANT", 0x20000),
+    ("ECHO", 0x40000),
+];
+
+/// The bit of `IFF_RUNNING`, never printed by `ip` which shows `NO-CARRIER` if it is not set
+/// on an interface that is up
+const IFF_RUNNING: u32 = 0x40;
+
+/// Rebuild the flags integer from the names of the link flags
+fn flags_to_raw(flags: &[String]) -> u32 {
+    let mut raw = flags
+        .iter()
+        .filter_map(|f| IFF_FLAGS.iter().find(|(name, _)| name == f))
+        .fold(0, |acc, (_, bit)| acc | bit);
+    if raw & 0x1 != 0 && !flags.iter().any(|f| f == "NO-CARRIER") {
+        raw |= IFF_RUNNING;
+    }
+    raw
+}
+
+/// Get the carrier state from the link flags: the kernel sets `LOWER_UP` when the device
+/// has a carrier but only reports it for the interfaces that are administratively up
+fn carrier(flags: &[String]) -> Option<bool> {
+    if flags.iter().any(|f| f == "UP") {
+        Some(flags.iter().any(|f| f == "LOWER_UP"))
+    } else {
+        None
+    }
+}
+
+/// Check the U/L bit, the second least significant bit of the first byte, of a MAC address
+fn is_locally_administered(mac: &str) -> Option<bool> {
+    if mac.split(':').count() != 6 {
+        return None;
+    }
+    let first = u8::from_str_radix(mac.split(':').next()?, 16).ok()?;
+    Some(first & 0x02 != 0)
+}
+
+/// Parse a MAC prefix to vendor table, one `<prefix> <vendor>` per line, e.g.
+/// `52:54:00 QEMU/KVM`
+///
+/// The prefixes are normalized to lowercase with `:` separators, `-` ones are accepted too
+fn parse_oui_db(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let mut parts = l.splitn(2, char::is_whitespace);
+            let prefix = parts.next()?.to_lowercase().replace('-', ":");
+            let vendor = parts.next()?.trim();
+            Some((prefix, vendor.to_string()))
+        })
+        .collect()
+}
+
+/// Find the vendor of the longest prefix of the table matching the MAC address
+fn lookup_vendor(db: &[(String, String)], mac: &str) -> Option<String> {
+    let mac = mac.to_lowercase();
+    db.iter()
+        .filter(|(prefix, _)| mac.starts_with(&prefix[..]))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, vendor)| vendor.to_string())
+}
+
+/// Extract the gateway and the device of the default routes from the output of
+/// `ip route show default`, e.g. `default via 10.244.0.1 dev eth0`
+///
+/// The routes without a gateway, e.g. `default dev eth0 scope link`, are skipped
+fn parse_default_routes(s: &str) -> Vec<(String, Option<String>)> {
+    lazy_static! {
+        static ref VIA_RE: Regex = Regex::new(r"\svia\s+(?P<v>\S+)").unwrap();
+        static ref DEV_RE: Regex = Regex::new(r"\sdev\s+(?P<v>\S+)").unwrap();
+    }
+    s.lines()
+        .filter_map(|l| {
+            let via = capture(&VIA_RE, l)?;
+            Some((via.to_string(), capture(&DEV_RE, l).map(|v| v.to_string())))
+        })
+        .collect()
+}
+
+/// Get the names of the bridges with VLAN filtering enabled from the output of
+/// `ip -d link show type bridge`
+fn parse_vlan_filtering_bridges(printout: &str) -> Vec<String> {
+    lazy_static! {
+        static ref VLAN_FILTERING_RE: Regex = Regex::new(r"\svlan_filtering\s+1(?:\s|$)").unwrap();
+    }
+    let mut res = vec![];
+    let mut name = None;
+    for line in printout.lines() {
+        if let Some(caps) = HEADER_RE.captures(line) {
+            name = Some(caps["name"].to_string());
+        }
+        if VLAN_FILTERING_RE.is_match(line) {
+            res.extend(name.take());
+        }
+    }
+    res
+}
+
+/// Parse the VLANs of every port from the output of `bridge vlan show`, e.g.
+///
+/// ```text
+/// port              vlan-id
+/// veth0c97cb60      1 PVID Egress Untagged
+///                   20-25
+/// ```
+///
+/// The first column is only set on the first VLAN of the port, the lines of the port
+/// details, e.g. `state forwarding` with `-d`, are ignored
+fn parse_bridge_vlans(printout: &str) -> HashMap<String, BridgeVlans> {
+    lazy_static! {
+        static ref VLAN_RE: Regex = Regex::new(r"^(?P<id>\d+(?:-\d+)?)(?P<flags>\s.*)?$").unwrap();
+    }
+    let mut res: HashMap<String, BridgeVlans> = HashMap::new();
+    let mut port: Option<String> = None;
+    for line in printout.lines().filter(|l| !l.trim().is_empty()) {
+        let spec = if line.starts_with(char::is_whitespace) {
+            line.trim()
+        } else {
+            let mut parts = line.splitn(2, char::is_whitespace);
+            let name = parts.next().unwrap_or_default();
+            if name == "port" {
+                continue;
+            }
+            port = Some(name.to_string());
+            parts.next().unwrap_or_default().trim()
+        };
+        let (port, caps) = match (&port, VLAN_RE.captures(spec)) {
+            (Some(p), Some(c)) => (p, c),
+            _ => continue,
+        };
+        let id = caps["id"].to_string();
+        let flags = caps.name("flags").map_or("", |m| m.as_str());
+        let vlans = res.entry(port.clone()).or_default();
+        if flags.contains("PVID") {
+            vlans.pvid = id.parse().ok();
+        }
+        if flags.contains("Egress Untagged") {
+            vlans.untagged.push(id);
+        } else {
+            vlans.tagged.push(id);
+        }
+    }
+    res
+}
+
+/// Extract the state of the neighbor entry from the output of `ip neigh show <address>`,
+/// e.g. `10.244.0.1 dev eth0 lladdr 0a:58:0a:f4:00:01 REACHABLE`
+fn parse_neigh_state(s: &str) -> Option<String> {
+    s.lines()
+        .next()
+        .and_then(|l| l.split_whitespace().last())
+        .map(|v| v.to_string())
+}
+
+/// Return the `v` named group of the first match of `re` in `s`
+fn capture<'a>(re: &Regex, s: &'a str) -> Option<&'a str> {
+    re.captures(s).and_then(|m| m.name("v")).map(|v| v.as_str())
+}
+
+/// Parse the content of `/proc/net/dev` and extract the counters of every interface
+fn parse_proc_net_dev(content: &str) -> Result<HashMap<String, NetDevStats>, Error> {
+    let err = error::ProcNetDevParseErr;
+    let mut res = HashMap::new();
+    // the first two lines are the header
+    for line in content.lines().skip(2) {
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().ok_or(err)?.trim();
+        let values = parts
+            .next()
+            .ok_or(err)?
+            .split_whitespace()
+            .map(|v| v.parse())
+            .collect::<Result<Vec<u64>, _>>()?;
+        if values.len() < 10 {
+            Err(err)?
+        }
+        let stats = NetDevStats {
+            rx_bytes: values[0],
+            rx_packets: values[1],
+            tx_bytes: values[8],
+            tx_packets: values[9],
+        };
+        res.insert(name.to_string(), stats);
+    }
+    Ok(res)
+}
+
 /// Run a command on the host and return the trimmed output.
 /// Raise an error if the command did not run successfully
 fn run_host_cmd(cmd: &str) -> Result<String, Error> {
@@ -367,6 +3830,12 @@ fn run_host_cmd(cmd: &str) -> Result<String, Error> {
         Some(v) => v,
         None => Err(error::HostCmdError::CmdInvalid(cmd.to_string()))?,
     };
+    run_host_prog(prog, args)
+}
+
+/// Same as `run_host_cmd()` but the arguments are passed as they are, e.g. the names read
+/// from the pod annotations which may contain spaces
+fn run_host_prog(prog: &str, args: &[&str]) -> Result<String, Error> {
     debug!("running '{}' with args {:?}", prog, args);
 
     let output = Command::new(prog).args(args).output()?;
@@ -384,7 +3853,10 @@ fn run_host_cmd(cmd: &str) -> Result<String, Error> {
             .map(|c| c.to_string())
             .unwrap_or("N/A".to_string());
         Err(error::HostCmdError::CmdFailed {
-            cmd: cmd.to_string(),
+            cmd: std::iter::once(prog)
+                .chain(args.iter().cloned())
+                .collect::<Vec<_>>()
+                .join(" "),
             code,
             stderr: se.to_string(),
         })?
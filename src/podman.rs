@@ -0,0 +1,79 @@
+use super::error::PodmanError;
+use failure::{Error, ResultExt};
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+
+/// The version of the libpod API used in the request paths, served by podman 2.0 and newer
+const API_VERSION: &str = "v2.0.0";
+
+/// Fetch the `inspect` json of a container from the Podman REST API listening on the
+/// given unix socket, e.g. `/run/podman/podman.sock`
+pub fn inspect_container(socket: &str, id: &str) -> Result<serde_json::Value, Error> {
+    debug!("inspecting podman container {} via {}", id, socket);
+    get(socket, &format!("containers/{}/json", id))
+}
+
+/// List the ids of the running containers of the Podman REST API listening on the given
+/// unix socket
+pub fn list_containers(socket: &str) -> Result<Vec<String>, Error> {
+    debug!(
+        "fetching the ids of the running podman containers via {}",
+        socket
+    );
+    let body = get(socket, "containers/json")?;
+    let ids = match body.as_array() {
+        Some(v) => v.iter().filter_map(|c| c["Id"].as_str()),
+        None => Err(PodmanError::InvalidResponse)?,
+    };
+    Ok(ids.map(|s| s.to_string()).collect())
+}
+
+/// Get the version of the podman service listening on the given unix socket
+pub fn version(socket: &str) -> Result<String, Error> {
+    let body = get(socket, "version")?;
+    match body["Version"].as_str() {
+        Some(v) => Ok(v.to_string()),
+        None => Err(PodmanError::MissingField("Version"))?,
+    }
+}
+
+/// Send a `GET` request for the given libpod API path, e.g. `containers/json`, and return
+/// the json body of the response
+///
+/// HTTP/1.0 is used so the response is neither chunked nor kept alive and can be read
+/// until the socket is closed
+fn get(socket: &str, path: &str) -> Result<serde_json::Value, Error> {
+    let mut stream = UnixStream::connect(socket)
+        .context(format!("failed to connect to the podman socket {}", socket))?;
+    write!(
+        stream,
+        "GET /{}/libpod/{} HTTP/1.0\r\nHost: podman\r\n\r\n",
+        API_VERSION, path
+    )?;
+    let mut resp = String::new();
+    stream.read_to_string(&mut resp)?;
+    trace!("podman response:\n{}", resp);
+    parse_response(&resp)
+}
+
+/// Parse the json body of a Podman API response
+///
+/// The API reports the errors, e.g. an unknown container, as an object with a `message`
+pub fn parse_response(resp: &str) -> Result<serde_json::Value, Error> {
+    let (head, body) = match resp.find("\r\n\r\n") {
+        Some(i) => (&resp[..i], &resp[i + 4..]),
+        None => Err(PodmanError::InvalidResponse)?,
+    };
+    let status: u16 = match head.split_whitespace().nth(1).map(|s| s.parse()) {
+        Some(Ok(v)) => v,
+        _ => Err(PodmanError::InvalidResponse)?,
+    };
+    let body: serde_json::Value = serde_json::from_str(body)?;
+    if status != 200 {
+        Err(PodmanError::RequestFailed {
+            status,
+            message: body["message"].as_str().unwrap_or("-").to_string(),
+        })?
+    }
+    Ok(body)
+}
@@ -0,0 +1,158 @@
+use super::{k8s, netns_id, podman, run_host_cmd, tabify, Config, EXIT_CODE_ERROR};
+use tabwriter::TabWriter;
+
+/// The capabilities needed to enter the network namespace of the containers (`setns`)
+/// and to read the namespace links of the processes of other users
+const REQUIRED_CAPS: &[(&str, u32)] = &[("CAP_SYS_ADMIN", 21), ("CAP_SYS_PTRACE", 19)];
+
+/// The outcome of a check, `skip` is used for the checks that don't apply, e.g. the
+/// kubernetes API when there is no kubeconfig
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Fail,
+    Skip,
+}
+
+/// The result of one of the checks run by `selftest`
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, res: Result<String, String>) -> Self {
+        let (status, detail) = match res {
+            Ok(v) => (Status::Pass, v),
+            Err(v) => (Status::Fail, v),
+        };
+        Check {
+            name,
+            status,
+            detail,
+        }
+    }
+}
+
+/// Run every check of the environment cniguru needs, in order
+///
+/// The checks are independent so a failed one does not stop the others
+pub fn run(cfg: &Config) -> Vec<Check> {
+    vec![
+        Check::new("capabilities", check_capabilities()),
+        Check::new("proc", check_proc(cfg)),
+        Check::new("sysfs", check_sysfs()),
+        Check::new("netns-pid-1", check_netns(cfg)),
+        Check::new("container-runtime", check_container_runtime(cfg)),
+        check_kube_api(),
+    ]
+}
+
+/// Find the required capabilities missing from the `CapEff` mask of a
+/// `/proc/<pid>/status` file
+pub fn missing_capabilities(status: &str) -> Option<Vec<&'static str>> {
+    let mask = status
+        .lines()
+        .find(|l| l.starts_with("CapEff:"))
+        .and_then(|l| u64::from_str_radix(l["CapEff:".len()..].trim(), 16).ok())?;
+    Some(
+        REQUIRED_CAPS
+            .iter()
+            .filter(|&&(_, bit)| mask & (1 << bit) == 0)
+            .map(|&(name, _)| name)
+            .collect(),
+    )
+}
+
+fn check_capabilities() -> Result<String, String> {
+    // the capabilities of cniguru itself so the local /proc is used, not --proc-path
+    let status = std::fs::read_to_string("/proc/self/status")
+        .map_err(|e| format!("failed to read /proc/self/status: {}", e))?;
+    match missing_capabilities(&status) {
+        Some(ref v) if v.is_empty() => Ok("all the required capabilities are present".into()),
+        Some(v) => Err(format!("missing {}", v.join(", "))),
+        None => Err("no CapEff in /proc/self/status".into()),
+    }
+}
+
+fn check_proc(cfg: &Config) -> Result<String, String> {
+    let path = format!("{}/1/status", cfg.proc_path);
+    match std::fs::read_to_string(&path) {
+        Ok(_) => Ok(format!("{} is readable", path)),
+        Err(e) => Err(format!("failed to read {}: {}", path, e)),
+    }
+}
+
+fn check_sysfs() -> Result<String, String> {
+    let path = "/sys/class/net";
+    match std::fs::read_dir(path) {
+        Ok(v) => Ok(format!("{} links in {}", v.count(), path)),
+        Err(e) => Err(format!("failed to read {}, is sysfs mounted? {}", path, e)),
+    }
+}
+
+fn check_netns(cfg: &Config) -> Result<String, String> {
+    match netns_id(cfg, 1) {
+        Some(v) => Ok(v),
+        None => Err(format!("failed to read {}/1/ns/net", cfg.proc_path)),
+    }
+}
+
+/// Check the runtime used for the containers, podman if --podman-socket is set, see
+/// `dc_runtime()`
+fn check_container_runtime(cfg: &Config) -> Result<String, String> {
+    match cfg.podman_socket {
+        Some(ref socket) => match podman::version(socket) {
+            Ok(v) => Ok(format!("podman {} via {}", v, socket)),
+            Err(e) => Err(e.to_string()),
+        },
+        None => match run_host_cmd("docker info --format {{.ServerVersion}}") {
+            Ok(v) => Ok(format!("docker {}", v)),
+            Err(e) => Err(e.to_string()),
+        },
+    }
+}
+
+fn check_kube_api() -> Check {
+    let name = "kube-api";
+    match k8s::check_api() {
+        Ok(Some(cfg)) => Check::new(name, Ok(format!("healthy, using {}", cfg))),
+        Ok(None) => Check {
+            name,
+            status: Status::Skip,
+            detail: "no kubeconfig found".into(),
+        },
+        Err(e) => Check::new(name, Err(e.to_string())),
+    }
+}
+
+/// Print the checks, as json or as a checklist, and exit with code 1 if any of them failed
+pub fn print_and_exit(checks: &[Check], json: bool) -> ! {
+    if json {
+        let s =
+            serde_json::to_string_pretty(checks).expect("failed to serialize the output to json");
+        println!("{}", s);
+    } else {
+        let mut r = vec!["CHECK\tSTATUS\tDETAIL".to_string()];
+        for c in checks {
+            let status = match c.status {
+                Status::Pass => "PASS",
+                Status::Fail => "FAIL",
+                Status::Skip => "SKIP",
+            };
+            r.push(format!("{}\t{}\t{}", c.name, status, c.detail));
+        }
+        let tw = TabWriter::new(Vec::<u8>::new());
+        println!(
+            "\n{}\n",
+            tabify(tw, &r.join("\n")).expect("failed to format the output")
+        );
+    }
+    if checks.iter().any(|c| c.status == Status::Fail) {
+        std::process::exit(EXIT_CODE_ERROR);
+    }
+    std::process::exit(0);
+}
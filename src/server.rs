@@ -0,0 +1,178 @@
+use super::error::{HostCmdError, K8sError, PodmanError};
+use super::{dc_runtime, gen_output_for_container, k8s, post_process, Args, Config, Container};
+use super::{Output, TargetError};
+use failure::{Error, ResultExt};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+/// The requests are handled one at a time so a slow or idle client can hold the listener
+/// for at most this long on every read or write
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The maximum size of the request line and headers, there is no body for a `GET`
+const MAX_REQUEST_HEAD_LEN: u64 = 8 * 1024;
+
+/// What a request asks to inspect
+#[derive(Debug, PartialEq, Eq)]
+pub enum Target<'a> {
+    // `GET /container/<id>`
+    Container(&'a str),
+    // `GET /pod/<namespace>/<name>`
+    Pod(&'a str, &'a str),
+}
+
+/// Serve the inspection of the containers over HTTP until killed, the requests are
+/// handled one at a time
+///
+/// The results are completed with `post_process` so the options given to `serve`,
+/// e.g. `--check-gateway`, apply to every request
+pub fn serve(args: &Args, cfg: &Config, addr: &str) -> Result<(), Error> {
+    let listener = TcpListener::bind(addr).context(format!("failed to listen on {}", addr))?;
+    eprintln!("listening on http://{}", addr);
+    for stream in listener.incoming() {
+        let res = stream
+            .map_err(Error::from)
+            .and_then(|s| handle_connection(args, cfg, s));
+        if let Err(e) = res {
+            debug!("failed to handle a request: {}", e);
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(args: &Args, cfg: &Config, mut stream: TcpStream) -> Result<(), Error> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let request_line = {
+        let mut reader = BufReader::new((&stream).take(MAX_REQUEST_HEAD_LEN));
+        read_request_line(&mut reader)?
+    };
+    debug!("request: {:?}", request_line);
+
+    let request_line = request_line.unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => match parse_path(path) {
+            Some(target) => match inspect(args, cfg, &target) {
+                Ok(v) => (200, serde_json::to_value(&v)?),
+                Err(e) => (
+                    error_status(&e),
+                    serde_json::to_value(TargetError::new(path.into(), &e))?,
+                ),
+            },
+            None if is_target_path(path) => {
+                (400, json!({ "error": format!("invalid id in {}", path) }))
+            }
+            None => (404, json!({ "error": format!("unknown path {}", path) })),
+        },
+        (Some(_), Some(_)) => (405, json!({ "error": "only GET is supported" })),
+        _ => (400, json!({ "error": "invalid request" })),
+    };
+
+    let body = serde_json::to_string_pretty(&body)?;
+    write!(
+        stream,
+        "HTTP/1.0 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Read the request line, the headers are read and ignored as there is no body for a `GET`
+///
+/// Return `None` if the request is cut, e.g. because it's larger than the reader limit
+pub fn read_request_line<R: BufRead>(reader: &mut R) -> Result<Option<String>, Error> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    if !request_line.ends_with('\n') {
+        return Ok(None);
+    }
+    let mut header = String::new();
+    loop {
+        header.clear();
+        reader.read_line(&mut header)?;
+        if !header.ends_with('\n') {
+            return Ok(None);
+        }
+        if header.trim().is_empty() {
+            return Ok(Some(request_line.trim().to_string()));
+        }
+    }
+}
+
+/// The status to reply with when the inspection failed, the targets that don't exist are
+/// not found instead of a server error
+pub fn error_status(e: &Error) -> u16 {
+    let not_found = e.iter_chain().any(|cause| {
+        if let Some(&PodmanError::RequestFailed { status, .. }) = cause.downcast_ref() {
+            return status == 404;
+        }
+        if let Some(HostCmdError::CmdFailed { stderr, .. }) = cause.downcast_ref() {
+            return stderr.contains("No such object") || stderr.contains("No such container");
+        }
+        if let Some(K8sError::KubeclientError(msg)) = cause.downcast_ref() {
+            return msg.ends_with("not found");
+        }
+        false
+    });
+    if not_found {
+        404
+    } else {
+        500
+    }
+}
+
+/// Check if the path has the shape of a target, e.g. `/container/<id>`, regardless of
+/// the characters of the ids
+pub fn is_target_path(path: &str) -> bool {
+    let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+    matches!(parts[..], ["container", _] | ["pod", _, _])
+}
+
+/// Find the target of the request from its path
+///
+/// The ids and names are passed to `docker` and `kubectl` so only the characters valid
+/// in them are accepted
+pub fn parse_path<'a>(path: &'a str) -> Option<Target<'a>> {
+    let valid = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    };
+    let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match parts[..] {
+        ["container", id] if valid(id) => Some(Target::Container(id)),
+        ["pod", namespace, name] if valid(namespace) && valid(name) => {
+            Some(Target::Pod(namespace, name))
+        }
+        _ => None,
+    }
+}
+
+fn inspect(args: &Args, cfg: &Config, target: &Target) -> Result<Vec<Output>, Error> {
+    let containers = match *target {
+        Target::Container(id) => vec![Container::new(cfg, id.to_string(), dc_runtime(cfg))?],
+        Target::Pod(namespace, name) => k8s::Pod::new(name, Some(namespace)).containers(cfg)?,
+    };
+    let mut output = containers
+        .into_iter()
+        .map(|c| gen_output_for_container(cfg, c))
+        .collect::<Result<Vec<Output>, Error>>()?;
+    post_process(args, cfg, &mut output)?;
+    Ok(output)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    }
+}
@@ -1,4 +1,109 @@
-use super::{parse_ip_link_or_addr_printout, VethIntf};
+use super::k8s::{
+    parse_ephemeral_container_ids, parse_networks_annotation, pod_names_by_ip, policies_selecting,
+};
+use super::podman::parse_response;
+use super::selftest::missing_capabilities;
+use super::{
+    carrier, check_report_version, check_requested_networks, diff_reports, drop_ipv6_addresses,
+    filter_interfaces, flags_to_raw, glob_to_regex, is_locally_administered, lookup_vendor,
+    map_concurrently, mark_related, netns_id, outputs_by_key, pair_interfaces, parse_bridge_vlans,
+    parse_default_routes, parse_ifindex_churn, parse_ip_link_or_addr_printout, parse_neigh_state,
+    parse_oui_db, parse_proc_net_dev, parse_vlan_filtering_bridges, read_container_id,
+    resolve_master_chain, run_with_timeout, short_id, sysctl_key_to_path, verbosity_level,
+    write_output_file, write_output_socket, AddrLifetime, Args, BridgeVlans, CniProfile, Config,
+    Container, ContainerRuntime, Gateway, IntfCount, IpAddr, NetDevStats, Output, OutputFormat,
+    Problem, TargetError, VethIntf, VethIntfPair, DEFAULT_MAX_MASTER_CHAIN_LEN, USAGE,
+};
+use docopt::Docopt;
+use error;
+use std::collections::HashMap;
+use std::time::Duration;
+
+fn gen_container(pod_ip: Option<&str>) -> Container {
+    Container {
+        id: "3e08cafbb6eb01558e86ba53f170b62855f0bf5a328a77dc2da278061ff7fdc8".into(),
+        container_name: Some("netshoot".into()),
+        pid: 26393,
+        node_name: Some("kh1".into()),
+        pod_name: Some("netshoot-57c7994b66-zxdsl".into()),
+        namespace: Some("default".into()),
+        pod_ip: pod_ip.map(|s| s.into()),
+        host_network: None,
+        host_pid: None,
+        host_ipc: None,
+        netns: None,
+        host_network_basis: None,
+        spec_host_network: None,
+        networks_annotation: None,
+        ephemeral: false,
+        qos_class: None,
+        network_policies: None,
+        runtime: ContainerRuntime::Docker,
+    }
+}
+
+fn gen_inet_addr(address: &str) -> IpAddr {
+    IpAddr {
+        family: "inet".into(),
+        address: address.into(),
+        scope: Some("global".into()),
+        flags: vec![],
+        valid_lft: Some(AddrLifetime::Forever),
+        preferred_lft: Some(AddrLifetime::Forever),
+    }
+}
+
+fn gen_intf_pair(name: &str, ip_address: Option<&str>) -> VethIntfPair {
+    VethIntfPair {
+        container: VethIntf {
+            name: name.into(),
+            altnames: vec![],
+            ifindex: 3,
+            peer_ifindex: 558,
+            link_type: "ether".into(),
+            flags: vec![
+                "BROADCAST".into(),
+                "MULTICAST".into(),
+                "UP".into(),
+                "LOWER_UP".into(),
+            ],
+            flags_raw: 0x11043,
+            mtu: 1460,
+            kind: None,
+            oper_state: None,
+            carrier: Some(true),
+            min_mtu: None,
+            max_mtu: None,
+            txqueuelen: None,
+            gso_max_size: None,
+            gso_max_segs: None,
+            gro_max_size: None,
+            num_tx_queues: None,
+            num_rx_queues: None,
+            linkmode: None,
+            mac_address: "0a:58:0a:f4:00:de".into(),
+            locally_administered: Some(true),
+            vendor: None,
+            broadcast: Some("ff:ff:ff:ff:ff:ff".into()),
+            bridge: None,
+            master_chain: vec![],
+            master_chain_truncated: false,
+            slave_kind: None,
+            slave_state: None,
+            ip_address: ip_address.map(|s| s.into()),
+            addresses: vec![],
+            phys_port_name: None,
+            phys_switch_id: None,
+            link: None,
+            link_netnsid: Some(0),
+        },
+        node: None,
+        traffic: None,
+        related: false,
+        network: None,
+        bridge_vlans: None,
+    }
+}
 
 #[test]
 fn test_parse_ip_link_printout_basic() {
@@ -24,27 +129,111 @@ fn test_parse_ip_link_printout_basic() {
     let exp = vec![
         VethIntf {
             name: "vethc3cef48b".into(),
+            altnames: vec![],
             ifindex: 2,
             peer_ifindex: 3,
             bridge: Some("cni0".into()),
+            master_chain: vec!["cni0".into()],
+            master_chain_truncated: false,
+            slave_kind: None,
+            slave_state: None,
+            link_type: "ether".into(),
+            flags: vec![
+                "BROADCAST".into(),
+                "MULTICAST".into(),
+                "UP".into(),
+                "LOWER_UP".into(),
+            ],
+            flags_raw: 0x11043,
             mtu: 1450,
+            kind: None,
+            oper_state: Some("UP".into()),
+            carrier: Some(true),
+            min_mtu: None,
+            max_mtu: None,
+            txqueuelen: None,
+            gso_max_size: None,
+            gso_max_segs: None,
+            gro_max_size: None,
+            num_tx_queues: None,
+            num_rx_queues: None,
+            linkmode: Some("DEFAULT".into()),
             mac_address: "e6:93:28:78:39:99".into(),
+            locally_administered: Some(true),
+            vendor: None,
+            broadcast: Some("ff:ff:ff:ff:ff:ff".into()),
             ip_address: None,
+            addresses: vec![],
+            phys_port_name: None,
+            phys_switch_id: None,
+            link: None,
+            link_netnsid: Some(0),
         },
         VethIntf {
             name: "veth551a254e".into(),
+            altnames: vec![],
             ifindex: 14,
             peer_ifindex: 3,
             bridge: Some("cni0".into()),
+            master_chain: vec!["cni0".into()],
+            master_chain_truncated: false,
+            slave_kind: None,
+            slave_state: None,
+            link_type: "ether".into(),
+            flags: vec![
+                "BROADCAST".into(),
+                "MULTICAST".into(),
+                "UP".into(),
+                "LOWER_UP".into(),
+            ],
+            flags_raw: 0x11043,
             mtu: 1450,
+            kind: None,
+            oper_state: Some("UP".into()),
+            carrier: Some(true),
+            min_mtu: None,
+            max_mtu: None,
+            txqueuelen: None,
+            gso_max_size: None,
+            gso_max_segs: None,
+            gro_max_size: None,
+            num_tx_queues: None,
+            num_rx_queues: None,
+            linkmode: Some("DEFAULT".into()),
             mac_address: "12:56:7d:9f:80:15".into(),
+            locally_administered: Some(true),
+            vendor: None,
+            broadcast: Some("ff:ff:ff:ff:ff:ff".into()),
             ip_address: None,
+            addresses: vec![],
+            phys_port_name: None,
+            phys_switch_id: None,
+            link: None,
+            link_netnsid: Some(1),
         },
     ];
 
-    let got = parse_ip_link_or_addr_printout(s).unwrap();
+    let (got, peerless): (Vec<VethIntf>, Vec<VethIntf>) =
+        parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN)
+            .unwrap()
+            .into_iter()
+            .partition(|i| i.peer_ifindex != 0);
 
     assert_eq!(exp, got);
+
+    // the Ethernet links without a peer are kept too, only the loopback is dropped
+    let names: Vec<&str> = peerless.iter().map(|i| &i.name[..]).collect();
+    assert_eq!(
+        names,
+        vec![
+            "enp0s31f6",
+            "wlp3s0",
+            "docker0",
+            "wwp0s20f0u5c2",
+            "flannel.1",
+            "cni0"
+        ]
+    );
 }
 
 #[test]
@@ -54,15 +243,48 @@ fn test_parse_ip_link_printout_multus() {
 
     let exp = vec![VethIntf {
         name: "veth987c7292".into(),
+        altnames: vec![],
         ifindex: 610,
         peer_ifindex: 5,
         bridge: Some("bla-bla-int0".into()),
+        master_chain: vec!["bla-bla-int0".into()],
+        master_chain_truncated: false,
+        slave_kind: None,
+        slave_state: None,
+        link_type: "ether".into(),
+        flags: vec![
+            "BROADCAST".into(),
+            "MULTICAST".into(),
+            "UP".into(),
+            "LOWER_UP".into(),
+        ],
+        flags_raw: 0x11043,
         mtu: 1500,
+        kind: None,
+        oper_state: Some("UP".into()),
+        carrier: Some(true),
+        min_mtu: None,
+        max_mtu: None,
+        txqueuelen: None,
+        gso_max_size: None,
+        gso_max_segs: None,
+        gro_max_size: None,
+        num_tx_queues: None,
+        num_rx_queues: None,
+        linkmode: Some("DEFAULT".into()),
         mac_address: "46:ed:60:c6:e9:73".into(),
+        locally_administered: Some(true),
+        vendor: None,
+        broadcast: Some("ff:ff:ff:ff:ff:ff".into()),
         ip_address: None,
+        addresses: vec![],
+        phys_port_name: None,
+        phys_switch_id: None,
+        link: None,
+        link_netnsid: Some(6),
     }];
 
-    let got = parse_ip_link_or_addr_printout(s).unwrap();
+    let got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
 
     assert_eq!(exp, got);
 }
@@ -93,43 +315,1862 @@ fn test_parse_ip_addr_printout_multus() {
     let exp = vec![
         VethIntf {
             name: "eth0".into(),
+            altnames: vec![],
             ifindex: 3,
             peer_ifindex: 545,
             bridge: None,
+            master_chain: vec![],
+            master_chain_truncated: false,
+            slave_kind: None,
+            slave_state: None,
+            link_type: "ether".into(),
+            flags: vec![
+                "BROADCAST".into(),
+                "MULTICAST".into(),
+                "UP".into(),
+                "LOWER_UP".into(),
+            ],
+            flags_raw: 0x11043,
             mtu: 1460,
+            kind: None,
+            oper_state: Some("UP".into()),
+            carrier: Some(true),
+            min_mtu: None,
+            max_mtu: None,
+            txqueuelen: None,
+            gso_max_size: None,
+            gso_max_segs: None,
+            gro_max_size: None,
+            num_tx_queues: None,
+            num_rx_queues: None,
+            linkmode: None,
             mac_address: "0a:58:0a:f4:00:d8".into(),
+            locally_administered: Some(true),
+            vendor: None,
+            broadcast: Some("ff:ff:ff:ff:ff:ff".into()),
             ip_address: Some("10.244.0.216/24".into()),
+            addresses: vec![gen_inet_addr("10.244.0.216/24")],
+            phys_port_name: None,
+            phys_switch_id: None,
+            link: None,
+            link_netnsid: Some(0),
         },
         VethIntf {
             name: "net0".into(),
+            altnames: vec![],
             ifindex: 5,
             peer_ifindex: 546,
             bridge: None,
+            master_chain: vec![],
+            master_chain_truncated: false,
+            slave_kind: None,
+            slave_state: None,
+            link_type: "ether".into(),
+            flags: vec![
+                "BROADCAST".into(),
+                "MULTICAST".into(),
+                "UP".into(),
+                "LOWER_UP".into(),
+            ],
+            flags_raw: 0x11043,
             mtu: 1500,
+            kind: None,
+            oper_state: Some("UP".into()),
+            carrier: Some(true),
+            min_mtu: None,
+            max_mtu: None,
+            txqueuelen: None,
+            gso_max_size: None,
+            gso_max_segs: None,
+            gro_max_size: None,
+            num_tx_queues: None,
+            num_rx_queues: None,
+            linkmode: None,
             mac_address: "0a:58:15:17:5f:01".into(),
+            locally_administered: Some(true),
+            vendor: None,
+            broadcast: Some("ff:ff:ff:ff:ff:ff".into()),
             ip_address: Some("21.23.95.1/25".into()),
+            addresses: vec![gen_inet_addr("21.23.95.1/25")],
+            phys_port_name: None,
+            phys_switch_id: None,
+            link: None,
+            link_netnsid: Some(0),
         },
         VethIntf {
             name: "net1".into(),
+            altnames: vec![],
             ifindex: 7,
             peer_ifindex: 547,
             bridge: None,
+            master_chain: vec![],
+            master_chain_truncated: false,
+            slave_kind: None,
+            slave_state: None,
+            link_type: "ether".into(),
+            flags: vec![
+                "BROADCAST".into(),
+                "MULTICAST".into(),
+                "UP".into(),
+                "LOWER_UP".into(),
+            ],
+            flags_raw: 0x11043,
             mtu: 1500,
+            kind: None,
+            oper_state: Some("UP".into()),
+            carrier: Some(true),
+            min_mtu: None,
+            max_mtu: None,
+            txqueuelen: None,
+            gso_max_size: None,
+            gso_max_segs: None,
+            gro_max_size: None,
+            num_tx_queues: None,
+            num_rx_queues: None,
+            linkmode: None,
             mac_address: "0a:58:15:17:60:01".into(),
+            locally_administered: Some(true),
+            vendor: None,
+            broadcast: Some("ff:ff:ff:ff:ff:ff".into()),
             ip_address: Some("21.23.96.1/25".into()),
+            addresses: vec![gen_inet_addr("21.23.96.1/25")],
+            phys_port_name: None,
+            phys_switch_id: None,
+            link: None,
+            link_netnsid: Some(0),
         },
         VethIntf {
             name: "net2".into(),
+            altnames: vec![],
             ifindex: 9,
             peer_ifindex: 548,
             bridge: None,
+            master_chain: vec![],
+            master_chain_truncated: false,
+            slave_kind: None,
+            slave_state: None,
+            link_type: "ether".into(),
+            flags: vec![
+                "BROADCAST".into(),
+                "MULTICAST".into(),
+                "UP".into(),
+                "LOWER_UP".into(),
+            ],
+            flags_raw: 0x11043,
             mtu: 1500,
+            kind: None,
+            oper_state: Some("UP".into()),
+            carrier: Some(true),
+            min_mtu: None,
+            max_mtu: None,
+            txqueuelen: None,
+            gso_max_size: None,
+            gso_max_segs: None,
+            gro_max_size: None,
+            num_tx_queues: None,
+            num_rx_queues: None,
+            linkmode: None,
             mac_address: "0a:58:15:17:61:01".into(),
+            locally_administered: Some(true),
+            vendor: None,
+            broadcast: Some("ff:ff:ff:ff:ff:ff".into()),
             ip_address: Some("21.23.97.1/25".into()),
+            addresses: vec![gen_inet_addr("21.23.97.1/25")],
+            phys_port_name: None,
+            phys_switch_id: None,
+            link: None,
+            link_netnsid: Some(0),
         },
     ];
 
-    let got = parse_ip_link_or_addr_printout(s).unwrap();
+    let got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
 
     assert_eq!(exp, got);
 }
+
+#[test]
+fn test_parse_ip_addr_printout_down_intf() {
+    let s = r#"1: lo: <LOOPBACK,UP,LOWER_UP> mtu 65536 qdisc noqueue state UNKNOWN group default qlen 1000
+    link/loopback 00:00:00:00:00:00 brd 00:00:00:00:00:00
+    inet 127.0.0.1/8 scope host lo
+       valid_lft forever preferred_lft forever
+3: eth0@if545: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1460 qdisc noqueue state UP group default
+    link/ether 0a:58:0a:f4:00:d8 brd ff:ff:ff:ff:ff:ff link-netnsid 0
+    inet 10.244.0.216/24 scope global eth0
+       valid_lft forever preferred_lft forever
+5: net0@if546: <BROADCAST,MULTICAST> mtu 1500 qdisc noqueue state DOWN group default qlen 1000
+    link/ether 0a:58:15:17:5f:01 brd ff:ff:ff:ff:ff:ff link-netnsid 0
+    inet 21.23.95.1/25 scope global net0
+       valid_lft forever preferred_lft forever"#;
+
+    let exp = vec![
+        VethIntf {
+            name: "eth0".into(),
+            altnames: vec![],
+            ifindex: 3,
+            peer_ifindex: 545,
+            bridge: None,
+            master_chain: vec![],
+            master_chain_truncated: false,
+            slave_kind: None,
+            slave_state: None,
+            link_type: "ether".into(),
+            flags: vec![
+                "BROADCAST".into(),
+                "MULTICAST".into(),
+                "UP".into(),
+                "LOWER_UP".into(),
+            ],
+            flags_raw: 0x11043,
+            mtu: 1460,
+            kind: None,
+            oper_state: Some("UP".into()),
+            carrier: Some(true),
+            min_mtu: None,
+            max_mtu: None,
+            txqueuelen: None,
+            gso_max_size: None,
+            gso_max_segs: None,
+            gro_max_size: None,
+            num_tx_queues: None,
+            num_rx_queues: None,
+            linkmode: None,
+            mac_address: "0a:58:0a:f4:00:d8".into(),
+            locally_administered: Some(true),
+            vendor: None,
+            broadcast: Some("ff:ff:ff:ff:ff:ff".into()),
+            ip_address: Some("10.244.0.216/24".into()),
+            addresses: vec![gen_inet_addr("10.244.0.216/24")],
+            phys_port_name: None,
+            phys_switch_id: None,
+            link: None,
+            link_netnsid: Some(0),
+        },
+        VethIntf {
+            name: "net0".into(),
+            altnames: vec![],
+            ifindex: 5,
+            peer_ifindex: 546,
+            bridge: None,
+            master_chain: vec![],
+            master_chain_truncated: false,
+            slave_kind: None,
+            slave_state: None,
+            link_type: "ether".into(),
+            flags: vec!["BROADCAST".into(), "MULTICAST".into()],
+            flags_raw: 0x1002,
+            mtu: 1500,
+            kind: None,
+            oper_state: Some("DOWN".into()),
+            carrier: None,
+            min_mtu: None,
+            max_mtu: None,
+            txqueuelen: Some(1000),
+            gso_max_size: None,
+            gso_max_segs: None,
+            gro_max_size: None,
+            num_tx_queues: None,
+            num_rx_queues: None,
+            linkmode: None,
+            mac_address: "0a:58:15:17:5f:01".into(),
+            locally_administered: Some(true),
+            vendor: None,
+            broadcast: Some("ff:ff:ff:ff:ff:ff".into()),
+            ip_address: Some("21.23.95.1/25".into()),
+            addresses: vec![gen_inet_addr("21.23.95.1/25")],
+            phys_port_name: None,
+            phys_switch_id: None,
+            link: None,
+            link_netnsid: Some(0),
+        },
+    ];
+
+    let got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+
+    assert_eq!(exp, got);
+}
+
+#[test]
+fn test_parse_ip_link_printout_txqueuelen() {
+    let s = r#"14: veth551a254e@if3: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1450 qdisc noqueue master cni0 state UP mode DEFAULT group default qlen 1000
+    link/ether 12:56:7d:9f:80:15 brd ff:ff:ff:ff:ff:ff link-netnsid 1"#;
+
+    let exp = vec![VethIntf {
+        name: "veth551a254e".into(),
+        altnames: vec![],
+        ifindex: 14,
+        peer_ifindex: 3,
+        bridge: Some("cni0".into()),
+        master_chain: vec!["cni0".into()],
+        master_chain_truncated: false,
+        slave_kind: None,
+        slave_state: None,
+        link_type: "ether".into(),
+        flags: vec![
+            "BROADCAST".into(),
+            "MULTICAST".into(),
+            "UP".into(),
+            "LOWER_UP".into(),
+        ],
+        flags_raw: 0x11043,
+        mtu: 1450,
+        kind: None,
+        oper_state: Some("UP".into()),
+        carrier: Some(true),
+        min_mtu: None,
+        max_mtu: None,
+        txqueuelen: Some(1000),
+        gso_max_size: None,
+        gso_max_segs: None,
+        gro_max_size: None,
+        num_tx_queues: None,
+        num_rx_queues: None,
+        linkmode: Some("DEFAULT".into()),
+        mac_address: "12:56:7d:9f:80:15".into(),
+        locally_administered: Some(true),
+        vendor: None,
+        broadcast: Some("ff:ff:ff:ff:ff:ff".into()),
+        ip_address: None,
+        addresses: vec![],
+        phys_port_name: None,
+        phys_switch_id: None,
+        link: None,
+        link_netnsid: Some(1),
+    }];
+
+    let got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+
+    assert_eq!(exp, got);
+}
+
+#[test]
+fn test_parse_proc_net_dev() {
+    let s = r#"Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:       0       0    0    0    0     0          0         0        0       0    0    0    0     0       0          0
+  eth0:   18626     164    0    0    0     0          0         0     5358      54    0    0    0     0       0          0"#;
+
+    let got = parse_proc_net_dev(s).unwrap();
+
+    assert_eq!(got.len(), 2);
+    assert_eq!(
+        got["eth0"],
+        NetDevStats {
+            rx_bytes: 18626,
+            rx_packets: 164,
+            tx_bytes: 5358,
+            tx_packets: 54,
+        }
+    );
+}
+
+#[test]
+fn test_glob_to_regex() {
+    let re = glob_to_regex("veth*").unwrap();
+    assert!(re.is_match("vethc3cef48b"));
+    assert!(!re.is_match("eth0"));
+
+    let re = glob_to_regex("net?").unwrap();
+    assert!(re.is_match("net0"));
+    assert!(!re.is_match("net10"));
+
+    let re = glob_to_regex("flannel.1").unwrap();
+    assert!(re.is_match("flannel.1"));
+    assert!(!re.is_match("flannelx1"));
+}
+
+#[test]
+fn test_parse_ip_link_printout_without_netnsid() {
+    // both ends of the veth pair are in the same namespace so there is no link-netnsid
+    let s = r#"20: veth0@veth1: <BROADCAST,MULTICAST,M-DOWN> mtu 1500 qdisc noop state DOWN mode DEFAULT group default qlen 1000
+    link/ether 8a:5e:a6:2c:1f:0b brd ff:ff:ff:ff:ff:ff
+21: veth1@if20: <BROADCAST,MULTICAST,M-DOWN> mtu 1500 qdisc noop state DOWN mode DEFAULT group default qlen 1000
+    link/ether 2e:3b:09:b8:62:4d brd ff:ff:ff:ff:ff:ff"#;
+
+    let exp = vec![VethIntf {
+        name: "veth1".into(),
+        altnames: vec![],
+        ifindex: 21,
+        peer_ifindex: 20,
+        bridge: None,
+        master_chain: vec![],
+        master_chain_truncated: false,
+        slave_kind: None,
+        slave_state: None,
+        link_type: "ether".into(),
+        flags: vec!["BROADCAST".into(), "MULTICAST".into(), "M-DOWN".into()],
+        flags_raw: 0x1002,
+        mtu: 1500,
+        kind: None,
+        oper_state: Some("DOWN".into()),
+        carrier: None,
+        min_mtu: None,
+        max_mtu: None,
+        txqueuelen: Some(1000),
+        gso_max_size: None,
+        gso_max_segs: None,
+        gro_max_size: None,
+        num_tx_queues: None,
+        num_rx_queues: None,
+        linkmode: Some("DEFAULT".into()),
+        mac_address: "2e:3b:09:b8:62:4d".into(),
+        locally_administered: Some(true),
+        vendor: None,
+        broadcast: Some("ff:ff:ff:ff:ff:ff".into()),
+        ip_address: None,
+        addresses: vec![],
+        phys_port_name: None,
+        phys_switch_id: None,
+        link: None,
+        link_netnsid: None,
+    }];
+
+    let mut got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+
+    // the peer of veth0 is given by name so its ifindex is not known
+    let veth0 = got.remove(0);
+    assert_eq!((&veth0.name[..], veth0.peer_ifindex), ("veth0", 0));
+    assert_eq!(exp, got);
+}
+
+#[test]
+fn test_output_pod_ip_match() {
+    let pairs = vec![
+        gen_intf_pair("eth0", Some("10.244.0.222/24")),
+        gen_intf_pair("net0", Some("10.8.8.6/24")),
+    ];
+    let output = Output::new(gen_container(Some("10.244.0.222")), pairs);
+    assert!(output.problems.is_empty());
+}
+
+#[test]
+fn test_output_pod_ip_mismatch() {
+    let pairs = vec![gen_intf_pair("eth0", Some("10.244.0.223/24"))];
+    let output = Output::new(gen_container(Some("10.244.0.222")), pairs);
+    assert_eq!(output.problems.len(), 1);
+
+    // no pod IP to check outside of the pod path
+    let pairs = vec![gen_intf_pair("eth0", None)];
+    let output = Output::new(gen_container(None), pairs);
+    assert!(output.problems.is_empty());
+}
+
+#[test]
+fn test_sysctl_key_to_path() {
+    assert_eq!(
+        sysctl_key_to_path("net.ipv4.ip_forward").unwrap(),
+        "/proc/sys/net/ipv4/ip_forward"
+    );
+    assert_eq!(
+        sysctl_key_to_path("net.ipv4.conf.all.rp_filter").unwrap(),
+        "/proc/sys/net/ipv4/conf/all/rp_filter"
+    );
+    assert!(sysctl_key_to_path("kernel.hostname").is_err());
+    assert!(sysctl_key_to_path("net..ipv4").is_err());
+    assert!(sysctl_key_to_path("net.ipv4/../../kernel").is_err());
+}
+
+#[test]
+fn test_parse_podman_response() {
+    let resp = "HTTP/1.1 200 OK\r\nApi-Version: 1.40\r\nContent-Type: application/json\r\n\r\n{\"Id\": \"3e08cafbb6eb\", \"State\": {\"Pid\": 26393}}";
+    let v = parse_response(resp).unwrap();
+    assert_eq!(v["State"]["Pid"], 26393);
+
+    let resp = "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\n\r\n{\"cause\": \"no such container\", \"message\": \"no container with name or ID \\\"netshoot\\\" found: no such container\", \"response\": 404}";
+    let e = parse_response(resp).unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "podman API request failed with status 404: no container with name or ID \"netshoot\" found: no such container"
+    );
+
+    assert!(parse_response("HTTP/1.1 200 OK").is_err());
+}
+
+#[test]
+fn test_write_output_file_gzip() {
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    let path = std::env::temp_dir().join(format!("cniguru-test-{}.json", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    write_output_file(&path, "[]", true).unwrap();
+
+    // the extension is appended and the temporary file is gone
+    let gz_path = format!("{}.gz", path);
+    assert!(!std::path::Path::new(&format!("{}.tmp", gz_path)).exists());
+    let mut content = String::new();
+    GzDecoder::new(std::fs::File::open(&gz_path).unwrap())
+        .read_to_string(&mut content)
+        .unwrap();
+    std::fs::remove_file(&gz_path).unwrap();
+    assert_eq!(content, "[]");
+}
+
+#[test]
+fn test_write_output_socket() {
+    use std::io::Read;
+    use std::os::unix::net::UnixListener;
+
+    let path = std::env::temp_dir().join(format!("cniguru-test-{}.sock", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    let err = write_output_socket(&path, "[]").unwrap_err();
+    assert!(err
+        .downcast_ref::<error::OutputSocketMissingErr>()
+        .is_some());
+
+    let listener = UnixListener::bind(&path).unwrap();
+    let reader = std::thread::spawn(move || {
+        let mut content = String::new();
+        listener
+            .accept()
+            .unwrap()
+            .0
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    });
+    write_output_socket(&path, "[]").unwrap();
+    let content = reader.join().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(content, "[]\n");
+}
+
+#[test]
+fn test_parse_ip_link_printout_details() {
+    // `ip -d` adds the MTU range and the link kind details
+    let s = r#"558: veth0c97cb60@if3: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1460 qdisc noqueue master cni0 state UP mode DEFAULT group default
+    link/ether 0a:20:94:a0:35:64 brd ff:ff:ff:ff:ff:ff link-netnsid 3 promiscuity 1 minmtu 68 maxmtu 65535
+    veth
+    bridge_slave state forwarding priority 32 cost 2 hairpin on guard off root_block off fastleave off learning on flood on port_id 0x8001 port_no 0x1 designated_port 32769 designated_cost 0 designated_bridge 8000.a:58:a:f4:0:1 designated_root 8000.a:58:a:f4:0:1 hold_timer    0.00 message_age_timer    0.00 forward_delay_timer    0.00 topology_change_ack 0 config_pending 0 proxy_arp off proxy_arp_wifi off mcast_router 1 mcast_fast_leave off mcast_flood on neigh_suppress off group_fwd_mask 0 group_fwd_mask_str 0x0 vlan_tunnel off isolated off addrgenmode eui64 numtxqueues 1 numrxqueues 1 gso_max_size 65536 gso_max_segs 65535"#;
+
+    let exp = vec![VethIntf {
+        name: "veth0c97cb60".into(),
+        altnames: vec![],
+        ifindex: 558,
+        peer_ifindex: 3,
+        bridge: Some("cni0".into()),
+        master_chain: vec!["cni0".into()],
+        master_chain_truncated: false,
+        slave_kind: Some("bridge".into()),
+        slave_state: Some("forwarding".into()),
+        link_type: "ether".into(),
+        flags: vec![
+            "BROADCAST".into(),
+            "MULTICAST".into(),
+            "UP".into(),
+            "LOWER_UP".into(),
+        ],
+        flags_raw: 0x11043,
+        mtu: 1460,
+        kind: Some("veth".into()),
+        oper_state: Some("UP".into()),
+        carrier: Some(true),
+        min_mtu: Some(68),
+        max_mtu: Some(65535),
+        txqueuelen: None,
+        gso_max_size: Some(65536),
+        gso_max_segs: Some(65535),
+        gro_max_size: None,
+        num_tx_queues: Some(1),
+        num_rx_queues: Some(1),
+        linkmode: Some("DEFAULT".into()),
+        mac_address: "0a:20:94:a0:35:64".into(),
+        locally_administered: Some(true),
+        vendor: None,
+        broadcast: Some("ff:ff:ff:ff:ff:ff".into()),
+        ip_address: None,
+        addresses: vec![],
+        phys_port_name: None,
+        phys_switch_id: None,
+        link: None,
+        link_netnsid: Some(3),
+    }];
+
+    let got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+
+    assert_eq!(exp, got);
+}
+
+#[test]
+fn test_parse_ip_link_printout_altnames() {
+    let s = r#"3: eth0@if558: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1460 qdisc noqueue state UP mode DEFAULT group default
+    link/ether 0a:58:0a:f4:00:de brd ff:ff:ff:ff:ff:ff link-netnsid 0
+    altname enp0s3
+    altname ens3"#;
+
+    let got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+    assert_eq!(
+        got[0].altnames,
+        vec!["enp0s3".to_string(), "ens3".to_string()]
+    );
+
+    // the --interface filter also matches the alternative names
+    let pair = VethIntfPair {
+        container: got[0].clone(),
+        node: None,
+        traffic: None,
+        related: false,
+        network: None,
+        bridge_vlans: None,
+    };
+    assert!(pair.name_matches(&glob_to_regex("enp0s*").unwrap()));
+    assert!(!pair.name_matches(&glob_to_regex("eno*").unwrap()));
+}
+
+#[test]
+fn test_output_host_network_mismatch() {
+    let mut container = gen_container(None);
+    container.spec_host_network = Some(false);
+    container.host_network = Some(true);
+    let output = Output::new(container, vec![gen_intf_pair("eth0", None)]);
+    assert_eq!(output.problems.len(), 1);
+
+    let mut container = gen_container(None);
+    container.spec_host_network = Some(true);
+    container.host_network = Some(true);
+    let output = Output::new(container, vec![gen_intf_pair("eth0", None)]);
+    assert!(output.problems.is_empty());
+}
+
+#[test]
+fn test_output_veth_pair_mismatch() {
+    let mut pair = gen_intf_pair("eth0", None);
+    let mut nintf = pair.container.clone();
+    nintf.name = "veth0c97cb60".into();
+    pair.node = Some(nintf.clone());
+    let output = Output::new(gen_container(None), vec![pair]);
+    assert!(output.problems.is_empty());
+
+    // the node side has a different MTU and is down
+    let mut pair = gen_intf_pair("eth0", None);
+    nintf.mtu = 1500;
+    nintf.flags.retain(|f| f != "UP");
+    pair.node = Some(nintf);
+    let output = Output::new(gen_container(None), vec![pair]);
+    let codes: Vec<&str> = output.problems.iter().map(|p| &p.code[..]).collect();
+    assert_eq!(codes, vec!["mtu-mismatch", "link-state-mismatch"]);
+}
+
+#[test]
+fn test_mark_related() {
+    let gen_bridged_pair = |cname: &str, nname: &str, bridge: &str| {
+        let mut pair = gen_intf_pair(cname, None);
+        let mut nintf = pair.container.clone();
+        nintf.name = nname.into();
+        nintf.bridge = Some(bridge.into());
+        pair.node = Some(nintf);
+        pair
+    };
+    let mut pairs = vec![
+        gen_bridged_pair("eth0", "veth0c97cb60", "cni0"),
+        gen_bridged_pair("net0", "veth74689fd2", "br_dc_test"),
+        gen_bridged_pair("net1", "veth1b2c3d4e", "br_dc_test"),
+    ];
+
+    // the pair on the same bridge as the matching one is pulled in
+    let re = glob_to_regex("net0").unwrap();
+    mark_related(&mut pairs, &re);
+    let related: Vec<bool> = pairs.iter().map(|p| p.related).collect();
+    assert_eq!(related, vec![false, false, true]);
+
+    // all the pairs plugged into a matching bridge are pulled in
+    let re = glob_to_regex("cni0").unwrap();
+    mark_related(&mut pairs, &re);
+    let related: Vec<bool> = pairs.iter().map(|p| p.related).collect();
+    assert_eq!(related, vec![true, false, false]);
+
+    // the parent link of a vlan and the vlans on top of a link are pulled in both ways
+    let mut vlan = gen_intf_pair("net1.100", None);
+    vlan.container.link = Some("net1".into());
+    pairs.push(vlan);
+    let re = glob_to_regex("net1.100").unwrap();
+    mark_related(&mut pairs, &re);
+    let related: Vec<bool> = pairs.iter().map(|p| p.related).collect();
+    assert_eq!(related, vec![false, false, true, false]);
+    let re = glob_to_regex("net1").unwrap();
+    mark_related(&mut pairs, &re);
+    let related: Vec<bool> = pairs.iter().map(|p| p.related).collect();
+    assert_eq!(related, vec![false, true, false, true]);
+
+    // same for the master of a container interface, e.g. a bridge inside the container
+    let mut br = gen_intf_pair("br0", None);
+    br.node = None;
+    pairs[0].container.bridge = Some("br0".into());
+    pairs.push(br);
+    let re = glob_to_regex("eth0").unwrap();
+    mark_related(&mut pairs, &re);
+    let related: Vec<bool> = pairs.iter().map(|p| p.related).collect();
+    assert_eq!(related, vec![false, false, false, false, true]);
+}
+
+#[test]
+fn test_filter_interfaces() {
+    let gen_bridged_pair = |cname: &str, nname: &str, bridge: &str| {
+        let mut pair = gen_intf_pair(cname, None);
+        let mut nintf = pair.container.clone();
+        nintf.name = nname.into();
+        nintf.bridge = Some(bridge.into());
+        pair.node = Some(nintf);
+        pair
+    };
+    let gen_pairs = || {
+        vec![
+            gen_bridged_pair("eth0", "veth0c97cb60", "cni0"),
+            gen_bridged_pair("net0", "veth74689fd2", "br_dc_test"),
+            gen_bridged_pair("net1", "veth1b2c3d4e", "br_dc_test"),
+            gen_bridged_pair("tunl0", "vethb00c2a94", "cni0"),
+        ]
+    };
+    let names = |pairs: &[VethIntfPair]| -> Vec<String> {
+        pairs.iter().map(|p| p.container.name.clone()).collect()
+    };
+    let glob = |p: &str| glob_to_regex(p).unwrap();
+
+    // several exclude patterns in one go, matching either end of the pairs
+    let mut pairs = gen_pairs();
+    filter_interfaces(
+        &mut pairs,
+        None,
+        &[glob("tunl0"), glob("veth0c97cb60")],
+        false,
+    );
+    assert_eq!(names(&pairs), vec!["net0", "net1"]);
+
+    // the include filter is applied first, then the exclude one
+    let mut pairs = gen_pairs();
+    filter_interfaces(&mut pairs, Some(&glob("net*")), &[glob("net1")], false);
+    assert_eq!(names(&pairs), vec!["net0"]);
+
+    // the related pairs pulled in by the include filter can be excluded too
+    let mut pairs = gen_pairs();
+    filter_interfaces(&mut pairs, Some(&glob("eth0")), &[glob("tunl*")], true);
+    assert_eq!(names(&pairs), vec!["eth0"]);
+
+    // an interface matching both filters is dropped
+    let mut pairs = gen_pairs();
+    filter_interfaces(&mut pairs, Some(&glob("net?")), &[glob("net?")], false);
+    assert!(pairs.is_empty());
+}
+
+#[test]
+fn test_usage() {
+    // docopt only parses the usage at runtime so make sure it stays valid
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.argv(vec!["cniguru", "dc", "3e08cafbb6eb"]).deserialize())
+        .unwrap();
+    assert!(args.cmd_dc);
+    assert_eq!(args.arg_id, "3e08cafbb6eb");
+
+    let argv = vec![
+        "cniguru",
+        "exec",
+        "dc",
+        "3e08cafbb6eb",
+        "--",
+        "ip",
+        "-d",
+        "addr",
+    ];
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.argv(argv).deserialize())
+        .unwrap();
+    assert!(args.cmd_exec && args.cmd_dc);
+    assert_eq!(args.arg_cmd, vec!["ip", "-d", "addr"]);
+
+    let argv = vec![
+        "cniguru",
+        "pods",
+        "--select",
+        "app=netshoot",
+        "-o",
+        "ndjson",
+    ];
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.argv(argv).deserialize())
+        .unwrap();
+    assert!(matches!(args.flag_o, Some(OutputFormat::Ndjson)));
+
+    let argv = vec![
+        "cniguru",
+        "pod",
+        "netshoot",
+        "--exclude-interface",
+        "lo",
+        "-v",
+        "--exclude-interface",
+        "tunl0",
+        "-v",
+    ];
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.argv(argv).deserialize())
+        .unwrap();
+    assert_eq!(args.flag_exclude_interface, vec!["lo", "tunl0"]);
+    assert_eq!(args.flag_verbose, 2);
+
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| {
+            d.argv(vec!["cniguru", "dc", "3e08cafbb6eb", "-o", "iproute2"])
+                .deserialize()
+        })
+        .unwrap();
+    assert!(matches!(args.flag_o, Some(OutputFormat::Iproute2)));
+
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| {
+            d.argv(vec!["cniguru", "resolve-ip", "10.244.0.222"])
+                .deserialize()
+        })
+        .unwrap();
+    assert!(args.cmd_resolve_ip);
+    assert_eq!(args.arg_ip, "10.244.0.222");
+
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| {
+            d.argv(vec!["cniguru", "pod-by-ip", "10.244.0.222"])
+                .deserialize()
+        })
+        .unwrap();
+    assert!(args.cmd_pod_by_ip);
+
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| {
+            d.argv(vec!["cniguru", "serve", "--listen", "0.0.0.0:9000"])
+                .deserialize()
+        })
+        .unwrap();
+    assert!(args.cmd_serve);
+    assert_eq!(args.flag_listen, Some("0.0.0.0:9000".to_string()));
+
+    let argv = vec![
+        "cniguru",
+        "pods",
+        "--select",
+        "app=netshoot",
+        "--timeout-per-target",
+        "10",
+    ];
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.argv(argv).deserialize())
+        .unwrap();
+    assert_eq!(args.flag_timeout_per_target, Some(10));
+}
+
+#[test]
+fn test_diff_reports() {
+    let a = vec![Output::new(
+        gen_container(None),
+        vec![
+            gen_intf_pair("eth0", Some("10.244.0.222/24")),
+            gen_intf_pair("net0", Some("10.8.8.6/24")),
+        ],
+    )];
+
+    // the reports are compared after a json round trip, as when loaded from files
+    let s = serde_json::to_string(&a).unwrap();
+    let mut b: Vec<Output> = serde_json::from_str(&s).unwrap();
+    assert!(diff_reports(&a, &b).is_empty());
+
+    b[0].interfaces[0].container.mtu = 1500;
+    b[0].interfaces[1].container.name = "net1".into();
+    let exp = vec![
+        "container 3e08cafbb6eb: eth0: mtu 1460 -> 1500".to_string(),
+        "container 3e08cafbb6eb: interface net0 removed".to_string(),
+        "container 3e08cafbb6eb: interface net1 added".to_string(),
+    ];
+    assert_eq!(diff_reports(&a, &b), exp);
+}
+
+#[test]
+fn test_diff_reports_short_id() {
+    // the reports can be edited by hand or come from `dc <name>`, the ids are not checked
+    let gen = |id: &str| {
+        let mut c = gen_container(None);
+        c.id = id.into();
+        Output::new(c, vec![gen_intf_pair("eth0", None)])
+    };
+    let a = vec![gen("netshoot")];
+    let b = vec![gen("netshoot-12ütest")];
+    assert_eq!(
+        diff_reports(&a, &b),
+        vec![
+            "container netshoot: removed".to_string(),
+            "container netshoot-12ütest: added".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_ip_link_printout_master_chain() {
+    // a veth plugged into a bridge with no master, next to a vlan on top of that bridge
+    // which is a port of another bridge
+    let s = r#"5: br-outer: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc noqueue state UP mode DEFAULT group default qlen 1000
+    link/ether 6e:4f:1a:2b:3c:4d brd ff:ff:ff:ff:ff:ff
+6: br-inner.100@br-inner: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc noqueue master br-outer state UP mode DEFAULT group default qlen 1000
+    link/ether 6e:4f:1a:2b:3c:4e brd ff:ff:ff:ff:ff:ff
+7: br-inner: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc noqueue state UP mode DEFAULT group default qlen 1000
+    link/ether 6e:4f:1a:2b:3c:4e brd ff:ff:ff:ff:ff:ff
+558: veth0c97cb60@if3: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc noqueue master br-inner state UP mode DEFAULT group default
+    link/ether 0a:20:94:a0:35:64 brd ff:ff:ff:ff:ff:ff link-netnsid 3"#;
+
+    let veth_chain = |s: &str| {
+        parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN)
+            .unwrap()
+            .into_iter()
+            .find(|i| i.name == "veth0c97cb60")
+            .unwrap()
+            .master_chain
+    };
+    let got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+    assert_eq!(got.len(), 4);
+    assert_eq!(veth_chain(s), vec!["br-inner".to_string()]);
+
+    // the vlan on top of the inner bridge is plugged into the outer bridge
+    let s = s.replace("master br-inner ", "master br-inner.100 ");
+    let exp: Vec<String> = vec!["br-inner.100".into(), "br-outer".into()];
+    assert_eq!(veth_chain(&s), exp);
+
+    // a bridge which is itself a port of another bridge
+    let s = s.replace(
+        "master br-inner.100 ",
+        "master br-inner ",
+    ).replace(
+        "7: br-inner: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc noqueue state UP",
+        "7: br-inner: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc noqueue master br-outer state UP",
+    );
+    let exp: Vec<String> = vec!["br-inner".into(), "br-outer".into()];
+    assert_eq!(veth_chain(&s), exp);
+}
+
+#[test]
+fn test_parse_networks_annotation() {
+    let nets =
+        parse_networks_annotation("macvlan-conf, other/sriov-net@sriov0", "default").unwrap();
+    let got: Vec<(&str, &str, &str)> = nets
+        .iter()
+        .map(|n| (&n.namespace[..], &n.name[..], &n.interface[..]))
+        .collect();
+    assert_eq!(
+        got,
+        vec![
+            ("default", "macvlan-conf", "net1"),
+            ("other", "sriov-net", "sriov0"),
+        ]
+    );
+
+    let val = r#"[{"name": "macvlan-conf"}, {"name": "sriov-net", "namespace": "other", "interface": "sriov0"}]"#;
+    assert_eq!(parse_networks_annotation(val, "default").unwrap(), nets);
+
+    assert!(parse_networks_annotation("other/", "default").is_err());
+    assert!(parse_networks_annotation(r#"[{"namespace": "other"}]"#, "default").is_err());
+}
+
+#[test]
+fn test_intf_count() {
+    let mut down = gen_intf_pair("net0", None);
+    down.container.flags.retain(|f| f != "UP");
+    let output = Output::new(gen_container(None), vec![gen_intf_pair("eth0", None), down]);
+    let count = IntfCount::new(&output);
+    assert_eq!((count.interfaces, count.up, count.down), (2, 1, 1));
+    assert_eq!(
+        count.pod,
+        Some("default/netshoot-57c7994b66-zxdsl".to_string())
+    );
+}
+
+#[test]
+fn test_parse_ephemeral_container_ids() {
+    let pod = json!({
+        "status": {
+            "ephemeralContainerStatuses": [
+                {
+                    "name": "debugger-x7k2p",
+                    "containerID": "docker://9f2d6c1b0a4e",
+                    "state": {"running": {"startedAt": "2019-03-02T10:12:41Z"}}
+                },
+                {
+                    "name": "debugger-q4m8z",
+                    "state": {"waiting": {"reason": "PodInitializing"}}
+                },
+                {
+                    "name": "debugger-h5t9w",
+                    "containerID": "docker://51c0e3a7d829",
+                    "state": {
+                        "terminated": {
+                            "exitCode": 0,
+                            "reason": "Completed",
+                            "startedAt": "2019-03-02T09:58:03Z",
+                            "finishedAt": "2019-03-02T10:03:17Z",
+                            "containerID": "docker://51c0e3a7d829"
+                        }
+                    }
+                }
+            ]
+        }
+    });
+    let ids = parse_ephemeral_container_ids(&pod).unwrap();
+    let got: Vec<(Option<&str>, &str)> = ids
+        .iter()
+        .map(|(name, _, id)| (name.as_ref().map(|s| &s[..]), &id[..]))
+        .collect();
+    assert_eq!(got, vec![(Some("debugger-x7k2p"), "9f2d6c1b0a4e")]);
+
+    // no ephemeral containers at all
+    assert!(parse_ephemeral_container_ids(&json!({"status": {}}))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_parse_ip_addr_printout_ipv6_flags() {
+    let s = r#"3: eth0@if545: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1460 qdisc noqueue state UP group default
+    link/ether 0a:58:0a:f4:00:d8 brd ff:ff:ff:ff:ff:ff link-netnsid 0
+    inet 10.244.0.216/24 brd 10.244.0.255 scope global dynamic eth0
+       valid_lft 3599sec preferred_lft 3599sec
+    inet6 2001:db8::5c1e:2ff:fe6d:1a2b/64 scope global temporary deprecated dynamic
+       valid_lft 86303sec preferred_lft 0sec
+    inet6 fe80::858:aff:fef4:d8/64 scope link tentative
+       valid_lft forever preferred_lft forever"#;
+
+    let got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+    let addrs: Vec<(&str, &str, Option<&str>, Vec<&str>)> = got[0]
+        .addresses
+        .iter()
+        .map(|a| {
+            (
+                &a.family[..],
+                &a.address[..],
+                a.scope.as_ref().map(|s| &s[..]),
+                a.flags.iter().map(|f| &f[..]).collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        addrs,
+        vec![
+            ("inet", "10.244.0.216/24", Some("global"), vec!["dynamic"]),
+            (
+                "inet6",
+                "2001:db8::5c1e:2ff:fe6d:1a2b/64",
+                Some("global"),
+                vec!["temporary", "deprecated", "dynamic"]
+            ),
+            (
+                "inet6",
+                "fe80::858:aff:fef4:d8/64",
+                Some("link"),
+                vec!["tentative"]
+            ),
+        ]
+    );
+
+    // the tentative address is reported as a warning
+    let mut pair = gen_intf_pair("eth0", Some("10.244.0.216/24"));
+    pair.container.addresses = got[0].addresses.clone();
+    let output = Output::new(gen_container(None), vec![pair]);
+    assert_eq!(output.problems.len(), 1);
+}
+
+#[test]
+fn test_parse_default_routes() {
+    let s = "default via 10.244.0.1 dev eth0\ndefault dev net0 scope link";
+    assert_eq!(
+        parse_default_routes(s),
+        vec![("10.244.0.1".to_string(), Some("eth0".to_string()))]
+    );
+
+    let s = "10.244.0.1 dev eth0 lladdr 0a:58:0a:f4:00:01 REACHABLE";
+    assert_eq!(parse_neigh_state(s), Some("REACHABLE".to_string()));
+    assert_eq!(parse_neigh_state(""), None);
+
+    let gw = Gateway {
+        address: "10.244.0.1".into(),
+        dev: Some("eth0".into()),
+        state: Some("FAILED".into()),
+    };
+    assert!(!gw.is_resolved());
+}
+
+#[test]
+fn test_check_report_version() {
+    let mut report = vec![Output::new(gen_container(None), vec![])];
+    assert!(check_report_version(&report).is_ok());
+
+    // the reports saved before the output was versioned are still supported
+    let mut v = serde_json::to_value(&report).unwrap();
+    v[0].as_object_mut().unwrap().remove("output_version");
+    let old: Vec<Output> = serde_json::from_value(v).unwrap();
+    assert_eq!(old[0].output_version, 0);
+    assert!(check_report_version(&old).is_ok());
+
+    report[0].output_version += 1;
+    assert!(check_report_version(&report).is_err());
+}
+
+#[test]
+fn test_check_requested_networks() {
+    let mut container = gen_container(None);
+    container.networks_annotation = Some("macvlan-conf, sriov-net@sriov0".into());
+    let pairs = vec![gen_intf_pair("eth0", None), gen_intf_pair("net1", None)];
+    let mut output = Output::new(container, pairs);
+    check_requested_networks(&mut output);
+    assert_eq!(
+        output.problems,
+        vec![Problem::error(
+            "network-missing",
+            "requested network default/sriov-net has no interface sriov0 in the container".into()
+        )]
+    );
+
+    // the annotation is set by the pod author, an invalid one is only a problem of the pod
+    output.container.networks_annotation = Some("other/".into());
+    output.problems.clear();
+    check_requested_networks(&mut output);
+    assert_eq!(output.problems.len(), 1);
+    assert_eq!(output.problems[0].code, "networks-annotation-invalid");
+}
+
+#[test]
+fn test_parse_ip_link_printout_switchdev() {
+    // the uplink and a VF representor of a NIC in switchdev mode, neither has a peer
+    let s = r#"4: ens1f0np0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc mq state UP mode DEFAULT group default qlen 1000
+    link/ether 0c:42:a1:5e:3b:10 brd ff:ff:ff:ff:ff:ff promiscuity 0 minmtu 68 maxmtu 9978 addrgenmode none numtxqueues 96 numrxqueues 72 gso_max_size 65536 gso_max_segs 65535 portname p0 switchid 103b5e0003a1420c parentbus pci parentdev 0000:3b:00.0
+    vf 0     link/ether 00:00:00:00:00:00 brd ff:ff:ff:ff:ff:ff, spoof checking off, link-state auto, trust off, query_rss off
+12: eth1: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc mq state UP mode DEFAULT group default qlen 1000
+    link/ether 5e:2b:81:0f:3a:c7 brd ff:ff:ff:ff:ff:ff promiscuity 0 minmtu 68 maxmtu 9978 addrgenmode eui64 numtxqueues 1 numrxqueues 1 gso_max_size 65536 gso_max_segs 65535 portname pf0vf0 switchid 103b5e0003a1420c"#;
+
+    let got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+    let ports: Vec<(&str, u16, Option<&str>)> = got
+        .iter()
+        .map(|i| {
+            (
+                &i.name[..],
+                i.peer_ifindex,
+                i.phys_port_name.as_ref().map(|v| &v[..]),
+            )
+        })
+        .collect();
+    assert_eq!(
+        ports,
+        vec![("ens1f0np0", 0, Some("p0")), ("eth1", 0, Some("pf0vf0"))]
+    );
+    assert_eq!(got[1].mac_address, "5e:2b:81:0f:3a:c7");
+    assert!(got
+        .iter()
+        .all(|i| i.phys_switch_id == Some("103b5e0003a1420c".to_string())));
+
+    // the VF moved into the container has no peer on the node either
+    let s = r#"45: net1: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc mq state UP mode DEFAULT group default qlen 1000
+    link/ether 5a:c2:7d:4e:91:0f brd ff:ff:ff:ff:ff:ff promiscuity 0 minmtu 68 maxmtu 9978 addrgenmode eui64 numtxqueues 8 numrxqueues 8 gso_max_size 65536 gso_max_segs 65535 parentbus pci parentdev 0000:3b:00.2"#;
+    let cintfs = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+    let pairs = pair_interfaces(cintfs, vec![]);
+    assert_eq!(pairs.len(), 1);
+    assert_eq!(pairs[0].container.name, "net1");
+    assert!(pairs[0].node.is_none());
+}
+
+#[test]
+fn test_intf_summary() {
+    let mut pairs = vec![gen_intf_pair("eth0", None), gen_intf_pair("net1", None)];
+    pairs[0].container.kind = Some("veth".into());
+    pairs[0].container.oper_state = Some("UP".into());
+    pairs[1].container.kind = Some("macvlan".into());
+    pairs[1].container.oper_state = Some("UP".into());
+    let output = Output::new(gen_container(None), pairs);
+    let kinds: Vec<(&str, usize)> = output
+        .summary
+        .kinds
+        .iter()
+        .map(|(k, v)| (&k[..], *v))
+        .collect();
+    assert_eq!(kinds, vec![("macvlan", 1), ("veth", 1)]);
+    assert_eq!(output.summary.oper_states.get("UP"), Some(&2));
+}
+
+#[test]
+fn test_read_container_id() {
+    assert_eq!(read_container_id("3e08cafbb6eb").unwrap(), "3e08cafbb6eb");
+
+    let path = std::env::temp_dir().join(format!("cniguru-test-{}.id", std::process::id()));
+    std::fs::write(&path, "docker://3e08cafbb6eb01558e86ba53f170b628\n").unwrap();
+    let id = read_container_id(&format!("@{}", path.display()));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(id.unwrap(), "3e08cafbb6eb01558e86ba53f170b628");
+
+    // an empty file is rejected naming the file instead of running `docker inspect ''`
+    let path = std::env::temp_dir().join(format!("cniguru-test-{}-empty.id", std::process::id()));
+    std::fs::write(&path, " \n").unwrap();
+    let res = read_container_id(&format!("@{}", path.display()));
+    std::fs::remove_file(&path).unwrap();
+    let e = res.unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        format!("no container id in {}", path.display())
+    );
+    assert!(e
+        .iter_chain()
+        .any(|c| c.downcast_ref::<error::InvalidArgsErr>().is_some()));
+    assert!(read_container_id("docker://").is_err());
+}
+
+#[test]
+fn test_short_id() {
+    assert_eq!(
+        short_id("3e08cafbb6eb01558e86ba53f170b62855f0bf5a328a77dc2da278061ff7fdc8"),
+        "3e08cafbb6eb"
+    );
+    // e.g. a podman container given by name
+    assert_eq!(short_id("netshoot"), "netshoot");
+    // an id cut in the middle of a character is kept whole
+    assert_eq!(short_id("netshoot-12ütest"), "netshoot-12ütest");
+}
+
+#[test]
+fn test_parse_ip_addr_printout_link_types() {
+    let s = r#"1: lo: <LOOPBACK,UP,LOWER_UP> mtu 65536 qdisc noqueue state UNKNOWN group default qlen 1000
+    link/loopback 00:00:00:00:00:00 brd 00:00:00:00:00:00 promiscuity 0 minmtu 0 maxmtu 0
+    inet 127.0.0.1/8 scope host lo
+       valid_lft forever preferred_lft forever
+2: tunl0@NONE: <NOARP> mtu 1480 qdisc noop state DOWN group default qlen 1000
+    link/ipip 0.0.0.0 brd 0.0.0.0 promiscuity 0 minmtu 0 maxmtu 0
+    ipip any remote any local any ttl inherit nopmtudisc numtxqueues 1 numrxqueues 1
+4: ppp0: <POINTOPOINT,MULTICAST,NOARP,UP,LOWER_UP> mtu 1492 qdisc fq_codel state UNKNOWN group default qlen 3
+    link/ppp promiscuity 0 minmtu 0 maxmtu 0
+3: eth0@if545: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1460 qdisc noqueue state UP group default
+    link/ether 0a:58:0a:f4:00:d8 brd ff:ff:ff:ff:ff:ff link-netnsid 0
+    inet 10.244.0.216/24 scope global eth0
+       valid_lft forever preferred_lft forever"#;
+
+    let got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+    let links: Vec<(&str, &str, u16, &str)> = got
+        .iter()
+        .map(|i| {
+            (
+                &i.name[..],
+                &i.link_type[..],
+                i.peer_ifindex,
+                &i.mac_address[..],
+            )
+        })
+        .collect();
+    assert_eq!(
+        links,
+        vec![
+            ("tunl0", "ipip", 0, "0.0.0.0"),
+            ("ppp0", "ppp", 0, ""),
+            ("eth0", "ether", 545, "0a:58:0a:f4:00:d8"),
+        ]
+    );
+    assert!(!got[0].is_ether() && got[2].is_ether());
+}
+
+#[test]
+fn test_lookup_vendor() {
+    let db = parse_oui_db(super::OUI_DB);
+    assert_eq!(
+        lookup_vendor(&db, "52:54:00:12:34:56"),
+        Some("QEMU/KVM".to_string())
+    );
+    assert_eq!(
+        lookup_vendor(&db, "0A:58:0A:F4:00:DE"),
+        Some("CNI (IP derived)".to_string())
+    );
+    assert_eq!(lookup_vendor(&db, "d2:ae:0b:9f:62:72"), None);
+
+    // the longest prefix wins
+    let db = parse_oui_db("# custom\n52:54 Some VM\n52-54-00-AB QEMU test lab\n");
+    assert_eq!(
+        lookup_vendor(&db, "52:54:00:ab:00:01"),
+        Some("QEMU test lab".to_string())
+    );
+    assert_eq!(
+        lookup_vendor(&db, "52:54:00:12:00:01"),
+        Some("Some VM".to_string())
+    );
+}
+
+#[test]
+fn test_is_locally_administered() {
+    // the CNI bridge plugin derives the address from the IP
+    assert_eq!(is_locally_administered("0a:58:0a:f4:00:de"), Some(true));
+    assert_eq!(is_locally_administered("00:02:c9:3e:1a:40"), Some(false));
+    assert_eq!(is_locally_administered("0.0.0.0"), None);
+}
+
+#[test]
+fn test_outputs_by_key() {
+    let mut other = gen_container(None);
+    other.container_name = Some("sidecar".into());
+    let output = vec![
+        Output::new(gen_container(None), vec![]),
+        Output::new(other, vec![]),
+    ];
+    let keys: Vec<String> = outputs_by_key(&output).unwrap().keys().cloned().collect();
+    assert_eq!(
+        keys,
+        vec![
+            "default/netshoot-57c7994b66-zxdsl/netshoot",
+            "default/netshoot-57c7994b66-zxdsl/sidecar",
+        ]
+    );
+
+    let output = vec![
+        Output::new(gen_container(None), vec![]),
+        Output::new(gen_container(None), vec![]),
+    ];
+    assert!(outputs_by_key(&output).is_err());
+}
+
+#[test]
+fn test_target_error_missing_entry() {
+    let e = error::NoRunningContainerErr("netshoot-57c7994b66-zxdsl".into()).into();
+    let e = TargetError::new("pod 'netshoot-57c7994b66-zxdsl'".into(), &e);
+    let entry = e.missing_entry();
+    assert_eq!(entry["target"], "pod 'netshoot-57c7994b66-zxdsl'");
+    assert_eq!(entry["found"], false);
+    assert!(entry["reason"]
+        .as_str()
+        .unwrap()
+        .contains("netshoot-57c7994b66-zxdsl"));
+}
+
+#[test]
+fn test_carrier() {
+    let flags = |v: &[&str]| v.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        carrier(&flags(&["BROADCAST", "UP", "LOWER_UP"])),
+        Some(true)
+    );
+    assert_eq!(
+        carrier(&flags(&["NO-CARRIER", "BROADCAST", "UP"])),
+        Some(false)
+    );
+    assert_eq!(carrier(&flags(&["BROADCAST", "MULTICAST"])), None);
+}
+
+#[test]
+fn test_has_address() {
+    let mut intf = gen_intf_pair("eth0", Some("10.244.0.222/24")).container;
+    intf.addresses = vec![gen_inet_addr("fe80::858:aff:fef4:de/64")];
+    assert!(intf.has_address(&"10.244.0.222".parse().unwrap()));
+    assert!(intf.has_address(&"fe80:0::858:aff:fef4:de".parse().unwrap()));
+    assert!(!intf.has_address(&"10.244.0.22".parse().unwrap()));
+}
+
+#[test]
+fn test_run_with_timeout() {
+    let res = run_with_timeout(Duration::from_secs(5), || Ok(42));
+    assert_eq!(res.unwrap(), 42);
+
+    let res = run_with_timeout(Duration::from_millis(10), || {
+        std::thread::sleep(Duration::from_secs(1));
+        Ok(42)
+    });
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_parse_ip_link_printout_bond_slave() {
+    let s = r#"7: eth1@if8: <BROADCAST,MULTICAST,SLAVE,UP,LOWER_UP> mtu 1500 qdisc noqueue master bond0 state UP mode DEFAULT group default
+    link/ether 0a:58:0a:f4:00:df brd ff:ff:ff:ff:ff:ff link-netnsid 0 promiscuity 0 minmtu 68 maxmtu 65535
+    veth
+    bond_slave state BACKUP mii_status UP link_failure_count 0 perm_hwaddr 0a:58:0a:f4:00:df queue_id 0 addrgenmode eui64"#;
+
+    let got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+    assert_eq!(got[0].slave_kind, Some("bond".to_string()));
+    assert_eq!(got[0].slave_state, Some("BACKUP".to_string()));
+    assert_eq!(got[0].kind, Some("veth".to_string()));
+}
+
+#[test]
+fn test_netns_id() {
+    let id = netns_id(&Config::default(), std::process::id()).unwrap();
+    assert!(id.starts_with("net:[") && id.ends_with(']'));
+}
+
+#[test]
+fn test_resolve_master_chain() {
+    let parents: HashMap<String, String> = vec![("br0", "br1"), ("br1", "br2"), ("br2", "br0")]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+    // a loop is only followed once around
+    let mut intf = gen_intf_pair("eth0", None).container;
+    intf.bridge = Some("br0".into());
+    resolve_master_chain(&mut intf, &parents, 8);
+    assert_eq!(intf.master_chain, vec!["br0", "br1", "br2"]);
+    assert!(!intf.master_chain_truncated);
+
+    let mut intf = gen_intf_pair("eth0", None).container;
+    intf.bridge = Some("br0".into());
+    resolve_master_chain(&mut intf, &parents, 2);
+    assert_eq!(intf.master_chain, vec!["br0", "br1"]);
+    assert!(intf.master_chain_truncated);
+}
+
+#[test]
+fn test_parse_ip_link_printout_gso_gro() {
+    let s = r#"3: eth0@if558: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1460 qdisc noqueue state UP mode DEFAULT group default
+    link/ether 0a:58:0a:f4:00:de brd ff:ff:ff:ff:ff:ff link-netnsid 0 promiscuity 0 minmtu 68 maxmtu 65535
+    veth addrgenmode eui64 numtxqueues 2 numrxqueues 2 gso_max_size 65536 gso_max_segs 65535 tso_max_size 524280 tso_max_segs 65535 gro_max_size 65536"#;
+
+    let got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+    assert_eq!(got[0].gso_max_size, Some(65536));
+    assert_eq!(got[0].gso_max_segs, Some(65535));
+    assert_eq!(got[0].gro_max_size, Some(65536));
+}
+
+#[test]
+fn test_pod_names_by_ip() {
+    let pods = json!({
+        "items": [
+            {
+                "metadata": {"name": "netshoot-57c7994b66-zxdsl"},
+                "status": {"podIP": "10.244.0.222", "podIPs": [{"ip": "10.244.0.222"}, {"ip": "fd00:10:244::de"}]}
+            },
+            {
+                "metadata": {"name": "kube-proxy-x7k2p"},
+                "status": {"podIP": "192.168.1.10"}
+            },
+            {
+                "metadata": {"name": "node-exporter-q9v4d"},
+                "status": {"podIP": "192.168.1.10"}
+            },
+            {
+                "metadata": {"name": "pending-6d5f8"},
+                "status": {}
+            }
+        ]
+    });
+    let names = |ip: &str| pod_names_by_ip(&pods, &ip.parse().unwrap());
+    assert_eq!(
+        names("fd00:10:244:0::de"),
+        vec!["netshoot-57c7994b66-zxdsl"]
+    );
+    // the pods using the host network share the node IP
+    assert_eq!(
+        names("192.168.1.10"),
+        vec!["kube-proxy-x7k2p", "node-exporter-q9v4d"]
+    );
+    assert!(names("10.244.0.1").is_empty());
+}
+
+#[test]
+fn test_intf_to_iproute2() {
+    let s = r#"3: eth0@if558: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1460 qdisc noqueue state UP group default
+    link/ether 0a:58:0a:f4:00:de brd ff:ff:ff:ff:ff:ff link-netnsid 0 promiscuity 0 minmtu 68 maxmtu 65535
+    veth numtxqueues 1 numrxqueues 1 gso_max_size 65536 gso_max_segs 65535
+    inet 10.244.0.222/24 brd 10.244.0.255 scope global eth0
+       valid_lft forever preferred_lft forever
+    inet6 fe80::858:aff:fef4:de/64 scope link tentative
+       valid_lft forever preferred_lft forever"#;
+
+    let got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+    let link = got[0].to_iproute2();
+    assert_eq!(link["ifindex"], 3);
+    assert_eq!(link["link_index"], 558);
+    assert_eq!(link["ifname"], "eth0");
+    assert_eq!(link["operstate"], "UP");
+    assert_eq!(link["address"], "0a:58:0a:f4:00:de");
+    assert_eq!(link["linkinfo"], json!({"info_kind": "veth"}));
+    // the fields that are not known are left out
+    assert!(link.get("master").is_none());
+    assert_eq!(
+        link["addr_info"],
+        json!([
+            {"family": "inet", "local": "10.244.0.222", "prefixlen": 24, "scope": "global",
+             "valid_life_time": 4294967295u32, "preferred_life_time": 4294967295u32},
+            {"family": "inet6", "local": "fe80::858:aff:fef4:de", "prefixlen": 64, "scope": "link", "tentative": true,
+             "valid_life_time": 4294967295u32, "preferred_life_time": 4294967295u32}
+        ])
+    );
+}
+
+#[test]
+fn test_map_concurrently() {
+    let items: Vec<u64> = (0..20).collect();
+    let got = map_concurrently(items, 8, |i| {
+        // the later items finish first
+        std::thread::sleep(Duration::from_millis(20 - i));
+        i * 2
+    });
+    assert_eq!(got, (0..20).map(|i| i * 2).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_pair_interfaces() {
+    // the names of the ends are not related, only the peer ifindex links them
+    let container = r#"3: eth0@if558: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1460 qdisc noqueue state UP mode DEFAULT group default
+    link/ether 0a:58:0a:f4:00:de brd ff:ff:ff:ff:ff:ff link-netnsid 0
+5: net1@if560: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc noqueue state UP mode DEFAULT group default
+    link/ether 0a:58:0a:08:08:06 brd ff:ff:ff:ff:ff:ff link-netnsid 0"#;
+    let node = r#"558: vethnet1@if3: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1460 qdisc noqueue master cni0 state UP mode DEFAULT group default
+    link/ether 0a:20:94:a0:35:64 brd ff:ff:ff:ff:ff:ff link-netnsid 3
+560: vetheth0@if5: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc noqueue master br0 state UP mode DEFAULT group default
+    link/ether 0a:20:94:a0:35:65 brd ff:ff:ff:ff:ff:ff link-netnsid 3"#;
+    let node_names = |node: &str| -> Vec<Option<String>> {
+        let cintfs =
+            parse_ip_link_or_addr_printout(container, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+        let nintfs = parse_ip_link_or_addr_printout(node, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+        pair_interfaces(cintfs, nintfs)
+            .into_iter()
+            .map(|p| p.node.map(|n| n.name))
+            .collect()
+    };
+
+    assert_eq!(
+        node_names(node),
+        vec![Some("vethnet1".to_string()), Some("vetheth0".to_string())]
+    );
+
+    // the peer of net1 is in another namespace, the node link with the same ifindex is not
+    // its peer, being the peer of an ifindex 5 in the namespace 4
+    let other = node.replace(
+        "br0 state UP mode DEFAULT group default\n    link/ether 0a:20:94:a0:35:65 brd ff:ff:ff:ff:ff:ff link-netnsid 3",
+        "br0 state UP mode DEFAULT group default\n    link/ether 0a:20:94:a0:35:65 brd ff:ff:ff:ff:ff:ff link-netnsid 4",
+    );
+    assert_ne!(other, node);
+    assert_eq!(node_names(&other), vec![Some("vethnet1".to_string()), None]);
+
+    // the peer ifindex is kept even if the peer can't be found, e.g. it's in a namespace
+    // that can't be read
+    let cintfs = parse_ip_link_or_addr_printout(container, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+    let pairs = pair_interfaces(cintfs, vec![]);
+    assert_eq!(pairs.len(), 2);
+    assert!(pairs.iter().all(|p| p.node.is_none()));
+    assert_eq!(pairs[1].container.peer_ifindex, 560);
+}
+
+#[test]
+fn test_drop_ipv6_addresses() {
+    let mut intf = gen_intf_pair("eth0", Some("10.244.0.222/24")).container;
+    let mut inet6 = gen_inet_addr("fe80::858:aff:fef4:de/64");
+    inet6.family = "inet6".into();
+    intf.addresses = vec![gen_inet_addr("10.244.0.222/24"), inet6];
+    let mut intfs = vec![intf];
+    drop_ipv6_addresses(&mut intfs);
+    assert_eq!(intfs[0].addresses, vec![gen_inet_addr("10.244.0.222/24")]);
+}
+
+#[test]
+fn test_parse_ifindex_churn() {
+    let s = r#"1: lo: <LOOPBACK,UP,LOWER_UP> mtu 65536 qdisc noqueue state UNKNOWN mode DEFAULT group default qlen 1000\    link/loopback 00:00:00:00:00:00 brd 00:00:00:00:00:00
+2: ens3: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc fq_codel state UP mode DEFAULT group default qlen 1000\    link/ether 52:54:00:12:34:56 brd ff:ff:ff:ff:ff:ff
+5: cni0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1450 qdisc noqueue state UP mode DEFAULT group default qlen 1000\    link/ether 0a:58:0a:f4:00:01 brd ff:ff:ff:ff:ff:ff
+610: veth0c97cb60@if3: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1450 qdisc noqueue master cni0 state UP mode DEFAULT group default \    link/ether 0a:20:94:a0:35:64 brd ff:ff:ff:ff:ff:ff link-netnsid 3"#;
+
+    let churn = parse_ifindex_churn(s).unwrap();
+    assert_eq!(churn.links, 4);
+    assert_eq!(churn.max_ifindex, 610);
+    assert_eq!(churn.gap, 606);
+
+    assert!(parse_ifindex_churn("").is_none());
+}
+
+#[test]
+fn test_cni_profile() {
+    let gen_output = |node_name: &str, bridge: Option<&str>| {
+        let mut pair = gen_intf_pair("eth0", Some("10.244.0.222/24"));
+        let mut node = gen_intf_pair(node_name, None).container;
+        node.bridge = bridge.map(|b| b.into());
+        pair.node = Some(node);
+        Output::new(gen_container(None), vec![pair])
+    };
+    let mut output = vec![
+        gen_output("cali6a1f3f7e2c4", None),
+        gen_output("veth0c97cb60", Some("cni0")),
+        gen_output("cali0d5e1b2a9f8", None),
+    ];
+    assert_eq!(CniProfile::detect(&output), Some(CniProfile::Calico));
+
+    for o in output.iter_mut() {
+        CniProfile::Calico.check(o);
+    }
+    assert!(output[0].problems.is_empty());
+    let codes: Vec<&str> = output[1].problems.iter().map(|p| &p.code[..]).collect();
+    assert_eq!(codes, vec!["cni-profile-mismatch", "cni-profile-mismatch"]);
+
+    let mut container = gen_container(None);
+    container.networks_annotation = Some("macvlan-conf".into());
+    let multus = Output::new(container, vec![]);
+    assert_eq!(CniProfile::guess(&multus), Some(CniProfile::Multus));
+    assert!(CniProfile::detect(&[Output::new(gen_container(None), vec![])]).is_none());
+}
+
+#[test]
+fn test_flags_to_raw() {
+    let flags = |v: &[&str]| v.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+    // IFF_RUNNING is set for the interfaces that are up and have a carrier
+    assert_eq!(
+        flags_to_raw(&flags(&["BROADCAST", "MULTICAST", "UP", "LOWER_UP"])),
+        0x11043
+    );
+    assert_eq!(
+        flags_to_raw(&flags(&["NO-CARRIER", "BROADCAST", "MULTICAST", "UP"])),
+        0x1003
+    );
+    assert_eq!(
+        flags_to_raw(&flags(&["BROADCAST", "MULTICAST", "M-DOWN"])),
+        0x1002
+    );
+}
+
+#[cfg(feature = "http-server")]
+#[test]
+fn test_server_parse_path() {
+    use super::server::{parse_path, Target};
+
+    assert_eq!(
+        parse_path("/container/3e08cafbb6eb"),
+        Some(Target::Container("3e08cafbb6eb"))
+    );
+    assert_eq!(
+        parse_path("/pod/default/netshoot-57c7994b66-zxdsl/"),
+        Some(Target::Pod("default", "netshoot-57c7994b66-zxdsl"))
+    );
+    // the ids are passed to the commands so anything else is rejected
+    assert_eq!(parse_path("/container/3e08cafbb6eb%20-f"), None);
+    assert_eq!(parse_path("/pod/default"), None);
+    assert_eq!(parse_path("/"), None);
+}
+
+#[cfg(feature = "http-server")]
+#[test]
+fn test_server_read_request_line() {
+    use super::server::read_request_line;
+    use std::io::{BufReader, Read};
+
+    let req = "GET /container/3e08cafbb6eb HTTP/1.1\r\nHost: kh1\r\nAccept: */*\r\n\r\n";
+    let read = |req: &str, limit: u64| {
+        let mut reader = BufReader::new(req.as_bytes().take(limit));
+        read_request_line(&mut reader).unwrap()
+    };
+    assert_eq!(
+        read(req, 8192),
+        Some("GET /container/3e08cafbb6eb HTTP/1.1".to_string())
+    );
+    // the request is cut by the limit, or the client closed the connection early
+    assert_eq!(read(req, 40), None);
+    assert_eq!(read("GET / HTTP/1.1\r\nHost: kh1\r\n", 8192), None);
+}
+
+#[cfg(feature = "http-server")]
+#[test]
+fn test_server_error_status() {
+    use super::server::{error_status, is_target_path};
+
+    let docker_err = |stderr: &str| -> failure::Error {
+        error::HostCmdError::CmdFailed {
+            cmd: "docker inspect 3e08cafbb6eb".into(),
+            code: "1".into(),
+            stderr: stderr.into(),
+        }
+        .into()
+    };
+    assert_eq!(
+        error_status(&docker_err("Error: No such object: 3e08cafbb6eb")),
+        404
+    );
+    assert_eq!(
+        error_status(&docker_err("Cannot connect to the Docker daemon")),
+        500
+    );
+    let k8s_err: failure::Error =
+        error::K8sError::KubeclientError("pods \"netshoot\" not found".into()).into();
+    assert_eq!(error_status(&k8s_err), 404);
+
+    assert!(is_target_path("/container/3e08cafbb6eb%20-f"));
+    assert!(!is_target_path("/pod/default"));
+}
+
+#[test]
+fn test_parse_bare_container_ids() {
+    let gen_pod = |cid: &str| {
+        json!({
+            "status": {"ephemeralContainerStatuses": [{
+                "name": "debugger-x7k2p",
+                "containerID": cid,
+                "state": {"running": {}}
+            }]}
+        })
+    };
+    let id = |cid: &str| parse_ephemeral_container_ids(&gen_pod(cid)).map(|mut v| v.remove(0).2);
+
+    // the ids without a scheme, or without the `//`, are used as docker ids
+    assert_eq!(id("9f2d6c1b0a4e").unwrap(), "9f2d6c1b0a4e");
+    assert_eq!(id("docker:9f2d6c1b0a4e").unwrap(), "9f2d6c1b0a4e");
+    assert!(id("containerd://9f2d6c1b0a4e").is_err());
+    assert!(id("not an id").is_err());
+}
+
+#[test]
+fn test_verbosity_level() {
+    let verbose = |argv: &[&str]| -> usize {
+        let args: Args = Docopt::new(USAGE)
+            .and_then(|d| d.argv(argv.iter()).deserialize())
+            .unwrap();
+        args.flag_verbose
+    };
+    assert_eq!(verbose(&["cniguru", "pod", "netshoot"]), 0);
+    assert_eq!(verbose(&["cniguru", "pod", "netshoot", "-vv"]), 2);
+    assert_eq!(
+        verbose(&["cniguru", "dc", "-v", "netshoot", "--verbose"]),
+        2
+    );
+
+    assert_eq!(verbosity_level(0), None);
+    assert_eq!(verbosity_level(1), Some(log::LevelFilter::Info));
+    assert_eq!(verbosity_level(2), Some(log::LevelFilter::Debug));
+    assert_eq!(verbosity_level(5), Some(log::LevelFilter::Trace));
+}
+
+#[test]
+fn test_missing_capabilities() {
+    let status = |cap_eff: &str| {
+        format!(
+            "Name:\tcniguru\nCapInh:\t0000000000000000\nCapEff:\t{}\n",
+            cap_eff
+        )
+    };
+    assert_eq!(
+        missing_capabilities(&status("000001ffffffffff")),
+        Some(vec![])
+    );
+    // the default docker capabilities, without CAP_SYS_ADMIN and CAP_SYS_PTRACE
+    assert_eq!(
+        missing_capabilities(&status("00000000a80425fb")),
+        Some(vec!["CAP_SYS_ADMIN", "CAP_SYS_PTRACE"])
+    );
+    assert_eq!(
+        missing_capabilities(&status("0000000000280000")),
+        Some(vec![])
+    );
+    assert_eq!(missing_capabilities("Name:\tcniguru\n"), None);
+}
+
+#[test]
+fn test_policies_selecting() {
+    let policies = json!({
+        "items": [
+            {"metadata": {"name": "default-deny"}, "spec": {"podSelector": {}}},
+            {"metadata": {"name": "allow-netshoot"}, "spec": {"podSelector": {"matchLabels": {"app": "netshoot"}}}},
+            {"metadata": {"name": "allow-nginx"}, "spec": {"podSelector": {"matchLabels": {"app": "nginx"}}}},
+            {"metadata": {"name": "debug-tiers"}, "spec": {"podSelector": {
+                "matchLabels": {"app": "netshoot"},
+                "matchExpressions": [
+                    {"key": "tier", "operator": "In", "values": ["debug", "test"]},
+                    {"key": "canary", "operator": "DoesNotExist"}
+                ]
+            }}},
+            {"metadata": {"name": "not-prod"}, "spec": {"podSelector": {
+                "matchExpressions": [{"key": "env", "operator": "NotIn", "values": ["prod"]}]
+            }}},
+            {"metadata": {"name": "bogus"}, "spec": {"podSelector": {
+                "matchExpressions": [{"key": "app", "operator": "Bogus"}]
+            }}}
+        ]
+    });
+
+    let labels = json!({"app": "netshoot", "tier": "debug"});
+    assert_eq!(
+        policies_selecting(&policies, &labels),
+        vec!["default-deny", "allow-netshoot", "debug-tiers", "not-prod"]
+    );
+
+    let labels = json!({"app": "netshoot", "tier": "prod", "env": "prod"});
+    assert_eq!(
+        policies_selecting(&policies, &labels),
+        vec!["default-deny", "allow-netshoot"]
+    );
+
+    // a pod without labels is only selected by the empty selector and by NotIn
+    assert_eq!(
+        policies_selecting(&policies, &serde_json::Value::Null),
+        vec!["default-deny", "not-prod"]
+    );
+}
+
+#[test]
+fn test_parse_bridge_vlans() {
+    let s = r#"2: cni0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1450 qdisc noqueue state UP mode DEFAULT group default qlen 1000
+    link/ether 0a:58:0a:f4:00:01 brd ff:ff:ff:ff:ff:ff promiscuity 0 minmtu 68 maxmtu 65535
+    bridge forward_delay 1500 hello_time 200 max_age 2000 ageing_time 30000 stp_state 0 priority 32768 vlan_filtering 0 vlan_protocol 802.1Q
+5: br_dc_test: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc noqueue state UP mode DEFAULT group default qlen 1000
+    link/ether 7a:1c:53:9f:2e:10 brd ff:ff:ff:ff:ff:ff promiscuity 0 minmtu 68 maxmtu 65535
+    bridge forward_delay 1500 hello_time 200 max_age 2000 ageing_time 30000 stp_state 0 priority 32768 vlan_filtering 1 vlan_protocol 802.1Q"#;
+    assert_eq!(parse_vlan_filtering_bridges(s), vec!["br_dc_test"]);
+
+    let s = "port              vlan-id
+br_dc_test        1 PVID Egress Untagged
+veth74689fd2      10 PVID Egress Untagged
+                  20
+                  30-35
+veth1b2c3d4e      20
+                    state forwarding mcast_router 1
+";
+    let vlans = parse_bridge_vlans(s);
+    assert_eq!(vlans.len(), 3);
+    assert_eq!(
+        vlans["veth74689fd2"],
+        BridgeVlans {
+            pvid: Some(10),
+            tagged: vec!["20".into(), "30-35".into()],
+            untagged: vec!["10".into()],
+        }
+    );
+    assert_eq!(
+        vlans["veth1b2c3d4e"],
+        BridgeVlans {
+            pvid: None,
+            tagged: vec!["20".into()],
+            untagged: vec![],
+        }
+    );
+}
+
+#[test]
+fn test_parse_ip_addr_lifetimes() {
+    let s = r#"3: eth0@if545: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1460 qdisc noqueue state UP group default
+    link/ether 0a:58:0a:f4:00:d8 brd ff:ff:ff:ff:ff:ff link-netnsid 0
+    inet 10.244.0.216/24 brd 10.244.0.255 scope global dynamic eth0
+       valid_lft 3599sec preferred_lft 3599sec
+    inet6 2001:db8::5c1e:2ff:fe6d:1a2b/64 scope global temporary deprecated dynamic
+       valid_lft 86303sec preferred_lft 0sec
+    inet6 fe80::858:aff:fef4:d8/64 scope link
+       valid_lft forever preferred_lft forever
+    inet6 2001:db8::1/64 scope global"#;
+
+    let got = parse_ip_link_or_addr_printout(s, DEFAULT_MAX_MASTER_CHAIN_LEN).unwrap();
+    let lifetimes: Vec<(Option<AddrLifetime>, Option<AddrLifetime>)> = got[0]
+        .addresses
+        .iter()
+        .map(|a| (a.valid_lft, a.preferred_lft))
+        .collect();
+    assert_eq!(
+        lifetimes,
+        vec![
+            (
+                Some(AddrLifetime::Secs(3599)),
+                Some(AddrLifetime::Secs(3599))
+            ),
+            (Some(AddrLifetime::Secs(86303)), Some(AddrLifetime::Secs(0))),
+            (Some(AddrLifetime::Forever), Some(AddrLifetime::Forever)),
+            // e.g. a printout saved with `ip -br` or edited by hand
+            (None, None),
+        ]
+    );
+    // the lifetimes are numbers, or the `forever` string, in the saved reports
+    let addrs: Vec<serde_json::Value> = got[0]
+        .addresses
+        .iter()
+        .map(|a| serde_json::to_value(a).unwrap())
+        .collect();
+    assert_eq!(addrs[1]["valid_lft"], json!(86303));
+    assert_eq!(addrs[1]["preferred_lft"], json!(0));
+    assert_eq!(addrs[2]["valid_lft"], json!("forever"));
+    assert_eq!(addrs[3]["valid_lft"], json!(null));
+    let s = serde_json::to_string(&got[0].addresses).unwrap();
+    let loaded: Vec<IpAddr> = serde_json::from_str(&s).unwrap();
+    assert_eq!(loaded, got[0].addresses);
+    assert!(serde_json::from_value::<AddrLifetime>(json!("3599sec")).is_err());
+}